@@ -0,0 +1,46 @@
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use criterion::{Criterion, Throughput};
+use slog::DrainExt;
+
+use std::io;
+use std::thread;
+
+const THREADS: usize = 4;
+const RECORDS_PER_THREAD: usize = 1000;
+
+/// Log from several threads, each through its own child logger, in compact mode
+fn log_threaded(context_cache: bool) {
+    let format = slog_html::new().compact().context_cache(context_cache).build().unwrap();
+    let root = slog::Logger::root(slog_stream::stream(io::sink(), format).fuse(),
+                                  o!("version" => env!("CARGO_PKG_VERSION")));
+    let server = root.new(o!("host" => "localhost", "port" => "8080"));
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|i| {
+            let peer = server.new(o!("peer_id" => i, "port" => 18230 + i));
+            thread::spawn(move || for n in 0..RECORDS_PER_THREAD {
+                debug!(peer, "message received"; "length" => n);
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+fn compact_threaded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compact_threaded");
+    group.throughput(Throughput::Elements((THREADS * RECORDS_PER_THREAD) as u64));
+    group.bench_function("context_cache", |b| b.iter(|| log_threaded(true)));
+    group.bench_function("no_context_cache", |b| b.iter(|| log_threaded(false)));
+    group.finish();
+}
+
+criterion_group!(benches, compact_threaded);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use criterion::{Criterion, Throughput};
+use slog::DrainExt;
+
+use std::io;
+
+fn logger(builder: slog_html::FormatBuilder) -> slog::Logger {
+    let root = slog::Logger::root(slog_stream::stream(io::sink(), builder.build().unwrap()).fuse(),
+                                  o!("version" => env!("CARGO_PKG_VERSION")));
+    root.new(o!("host" => "localhost", "port" => 8080))
+}
+
+fn bench_mode<F>(c: &mut Criterion, name: &str, build: F)
+    where F: Fn() -> slog_html::FormatBuilder
+{
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(1));
+
+    let log = logger(build());
+    let structured = logger(build().structured_values(true));
+    group.bench_function("message", |b| b.iter(|| info!(log, "listening")));
+    group.bench_function("values", |b| {
+        b.iter(|| info!(log, "response sent"; "status" => 200, "length" => 8, "path" => "/index.html"))
+    });
+    group.bench_function("structured", |b| {
+        b.iter(|| {
+            info!(structured, "request"; "body" => r#"{"id": 1, "tags": ["a", "b"], "ok": true}"#)
+        })
+    });
+    group.bench_function("multi_line", |b| {
+        b.iter(|| info!(log, "query"; "sql" => "SELECT *\nFROM records\nWHERE id < 10"))
+    });
+
+    group.finish();
+}
+
+fn full(c: &mut Criterion) {
+    bench_mode(c, "full", || slog_html::new().full());
+}
+
+fn compact(c: &mut Criterion) {
+    bench_mode(c, "compact", || slog_html::new().compact());
+}
+
+criterion_group!(benches, full, compact);
+criterion_main!(benches);
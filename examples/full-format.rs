@@ -16,11 +16,11 @@ fn main() {
 
     let d1 = slog_stream::stream(
         file,
-        slog_html::new().full().build()
+        slog_html::new().full().build().unwrap()
     );
     let d2 = slog_stream::stream(
         std::io::stderr(),
-        slog_html::new().full().build()
+        slog_html::new().full().build().unwrap()
     );
 
     let root_log = slog::Logger::root(
@@ -0,0 +1,41 @@
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::DrainExt;
+
+use std::fs::OpenOptions;
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Request {
+    method: &'static str,
+    path: &'static str,
+    headers: Vec<(&'static str, &'static str)>,
+}
+
+fn main() {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open("target/log.html").unwrap();
+
+    let log = slog::Logger::root(
+        slog_stream::stream(
+            file,
+            slog_html::new().structured_values(true).build().unwrap()
+        ).fuse(),
+        o!("version" => env!("CARGO_PKG_VERSION"))
+    );
+
+    let request = Request {
+        method: "GET",
+        path: "/index.html",
+        headers: vec![("Host", "localhost"), ("Accept", "text/html")],
+    };
+
+    info!(log, "request received"; "request" => format!("{:#?}", request));
+    info!(log, "response sent"; "body" => r#"{"status": 200, "items": [1, 2, {"id": "<3>"}], "next": null}"#);
+}
@@ -0,0 +1,368 @@
+use std::convert::TryFrom;
+use std::io;
+use std::io::BufRead;
+
+use slog::Level;
+
+use color_palette::ColorPalette;
+use decorator::{DocumentDecorator, HtmlDecorator, LINE_END, LINE_START, Tags};
+use style::{Style, StyleTable};
+
+/// Colors of the 16 basic terminal colors, as used by xterm
+const BASIC_COLORS: [&str; 16] = ["000000", "cd0000", "00cd00", "cdcd00", "0000ee",
+                                  "cd00cd", "00cdcd", "e5e5e5", "7f7f7f", "ff0000",
+                                  "00ff00", "ffff00", "5c5cff", "ff00ff", "00ffff",
+                                  "ffffff"];
+
+/// Converts terminal logs with ANSI SGR escape sequences to html
+///
+/// Every line of the log becomes a line of the same document structure `Format` writes.
+/// Colored level names, like the `INFO` of `slog-term`, take the level style and the color
+/// of the level in the palette. Other escape sequences and background colors are dropped.
+///
+/// ```
+/// # extern crate slog_html;
+/// # fn main() {
+/// let log = b"Oct 19 04:37:51.497 \x1b[32mINFO\x1b[0m \x1b[1mlistening\x1b[0m\n";
+///
+/// let mut html = Vec::new();
+/// slog_html::AnsiConverter::new()
+///     .document(false)
+///     .convert(&log[..], &mut html)
+///     .unwrap();
+///
+/// let html = String::from_utf8(html).unwrap();
+/// assert!(html.contains("<span style=\"font-weight:bold;\">listening</span>"));
+/// # }
+/// ```
+pub struct AnsiConverter {
+    color_palette: ColorPalette,
+    level_style: Style,
+    document: bool,
+}
+
+impl AnsiConverter {
+    /// New `AnsiConverter` with the default color palette and level style
+    pub fn new() -> Self {
+        AnsiConverter {
+            color_palette: ColorPalette::default(),
+            level_style: StyleTable::default().level,
+            document: true,
+        }
+    }
+
+    /// Set a custom color palette for level names
+    pub fn color_palette(mut self, color_palette: ColorPalette) -> Self {
+        self.color_palette = color_palette;
+        self
+    }
+
+    /// Set a custom style for level names
+    pub fn level_style(mut self, style: Style) -> Self {
+        self.level_style = style;
+        self
+    }
+
+    /// Write the document prologue and epilogue around the lines (default)
+    pub fn document(mut self, enabled: bool) -> Self {
+        self.document = enabled;
+        self
+    }
+
+    /// Convert every line of `input`, writing html to `output`
+    ///
+    /// Attributes set on one line stay active on the next, like in a terminal.
+    pub fn convert<R: BufRead, W: io::Write>(&self, input: R, mut output: W) -> io::Result<()> {
+        let decorator = HtmlDecorator::new(self.color_palette, StyleTable::default());
+        if self.document {
+            decorator.write_prologue(&mut output)?;
+        }
+        let mut sgr = Sgr::default();
+        for line in input.split(b'\n') {
+            let line = line?;
+            self.convert_line(&mut output, &String::from_utf8_lossy(&line), &mut sgr)?;
+        }
+        if self.document {
+            decorator.write_epilogue(&mut output)?;
+        }
+        output.flush()
+    }
+
+    /// Convert a single line, starting with the attributes in `sgr`
+    fn convert_line(&self, io: &mut dyn io::Write, line: &str, sgr: &mut Sgr) -> io::Result<()> {
+        io.write_all(LINE_START.as_bytes())?;
+        let mut rest = line;
+        while !rest.is_empty() {
+            let end = rest.find(|c: char| c == '\x1b' || (c.is_control() && c != '\t'))
+                .unwrap_or(rest.len());
+            self.write_segment(io, &rest[..end], sgr)?;
+            rest = &rest[end..];
+            if rest.starts_with('\x1b') {
+                rest = parse_escape(&rest[1..], sgr);
+            } else if let Some(c) = rest.chars().next() {
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        io.write_all(LINE_END.as_bytes())
+    }
+
+    /// Write text with the attributes in `sgr`
+    fn write_segment(&self, io: &mut dyn io::Write, text: &str, sgr: &Sgr) -> io::Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let level = if sgr.color.is_some() {
+            (1..).map_while(Level::from_usize).find(|level| level.as_short_str() == text.trim())
+        } else {
+            None
+        };
+        let tags = match level {
+            Some(level) => {
+                Tags::new(&self.level_style,
+                          Some(self.color_palette.level_to_color(level)))
+            }
+            None => Tags::new(&sgr.style(), sgr.color.map(Color::to_hex).as_deref()),
+        };
+        tags.fmt(io, &|io| io.write_all(text.as_bytes()))
+    }
+}
+
+impl Default for AnsiConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Foreground color set by an SGR sequence
+#[derive(Clone, Copy)]
+enum Color {
+    /// One of the 256 indexed colors
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn to_hex(self) -> String {
+        match self {
+            Color::Indexed(n) if n < 16 => BASIC_COLORS[n as usize].to_owned(),
+            Color::Indexed(n) if n < 232 => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+                let n = n - 16;
+                format!("{:02x}{:02x}{:02x}", level(n / 36), level(n / 6 % 6), level(n % 6))
+            }
+            Color::Indexed(n) => {
+                let gray = 8 + 10 * (n - 232);
+                format!("{:02x}{:02x}{:02x}", gray, gray, gray)
+            }
+            Color::Rgb(r, g, b) => format!("{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+/// Text attributes set by SGR sequences
+#[derive(Clone, Copy, Default)]
+struct Sgr {
+    color: Option<Color>,
+    bold: bool,
+    faint: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Sgr {
+    /// Style of text with these attributes, apart from its color
+    fn style(&self) -> Style {
+        Style {
+            color: None,
+            bold: self.bold,
+            italic: self.italic,
+            custom: match (self.underline, self.faint) {
+                (false, false) => None,
+                (true, false) => Some("text-decoration:underline;"),
+                (false, true) => Some("opacity:0.5;"),
+                (true, true) => Some("text-decoration:underline;opacity:0.5;"),
+            },
+        }
+    }
+
+    /// Apply the parameters of an SGR sequence
+    fn apply(&mut self, params: &str) {
+        // Empty parameters default to 0, numbers out of range are ignored like unknown ones
+        let mut params = params.split(';').map(|param| if param.is_empty() {
+            0
+        } else {
+            param.parse::<u32>().unwrap_or(u32::MAX)
+        });
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Sgr::default(),
+                1 => self.bold = true,
+                2 => self.faint = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.faint = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.color = Some(Color::Indexed(param as u8 - 30)),
+                90..=97 => self.color = Some(Color::Indexed(param as u8 - 90 + 8)),
+                39 => self.color = None,
+                38 | 48 => {
+                    let mut channel = || params.next().and_then(|n| u8::try_from(n).ok());
+                    let color = match channel() {
+                        Some(5) => channel().map(Color::Indexed),
+                        Some(2) => {
+                            match (channel(), channel(), channel()) {
+                                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    if let (38, Some(color)) = (param, color) {
+                        self.color = Some(color);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parse the escape sequence at the start of `rest`, following an escape character
+///
+/// Returns the text after the sequence.
+fn parse_escape<'a>(rest: &'a str, sgr: &mut Sgr) -> &'a str {
+    if let Some(csi) = rest.strip_prefix('[') {
+        // Parameters and intermediate bytes, up to the final byte
+        match csi.find(|c: char| ('\x40'..='\x7e').contains(&c)) {
+            Some(end) => {
+                if csi[end..].starts_with('m') {
+                    sgr.apply(&csi[..end]);
+                }
+                &csi[end + 1..]
+            }
+            None => "",
+        }
+    } else if let Some(osc) = rest.strip_prefix(']') {
+        // Operating system command, ended by BEL or ST
+        match osc.find(['\x07', '\x1b']) {
+            Some(end) if osc[end..].starts_with('\x07') => &osc[end + 1..],
+            Some(end) => osc[end + 1..].strip_prefix('\\').unwrap_or(&osc[end + 1..]),
+            None => "",
+        }
+    } else {
+        // Intermediate bytes, like the `(` of a character set designation, up to the final byte
+        let rest = rest.trim_start_matches(|c: char| ('\x20'..='\x2f').contains(&c));
+        rest.chars().next().map_or("", |c| &rest[c.len_utf8()..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Html of the lines of `input`, without the document around them
+    fn convert(input: &[u8]) -> String {
+        let mut html = Vec::new();
+        AnsiConverter::new().document(false).convert(input, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    /// Attributes after applying the escape sequences of `input` in turn
+    fn sgr(input: &str) -> Sgr {
+        let mut sgr = Sgr::default();
+        let mut rest = input;
+        while let Some(start) = rest.find('\x1b') {
+            rest = parse_escape(&rest[start + 1..], &mut sgr);
+        }
+        sgr
+    }
+
+    fn color(input: &str) -> Option<String> {
+        sgr(input).color.map(Color::to_hex)
+    }
+
+    #[test]
+    fn reset_clears_all_attributes() {
+        let reset = sgr("\x1b[1;3;4;31m\x1b[0m");
+        assert!(reset.color.is_none() && !reset.bold && !reset.italic && !reset.underline);
+        let reset = sgr("\x1b[1;31m\x1b[m");
+        assert!(reset.color.is_none() && !reset.bold);
+    }
+
+    #[test]
+    fn bold_and_faint() {
+        assert_eq!(convert(b"a\x1b[1mb\x1b[22mc\x1b[2md"),
+                   "<pre style=\"margin-bottom:-0.5em\">a<span style=\"font-weight:bold;\">b</span>\
+                    c<span style=\"opacity:0.5;\">d</span></pre>\n");
+        let normal = sgr("\x1b[1;2m\x1b[22m");
+        assert!(!normal.bold && !normal.faint);
+    }
+
+    #[test]
+    fn basic_colors() {
+        assert_eq!(color("\x1b[31m").unwrap(), "cd0000");
+        assert_eq!(color("\x1b[97m").unwrap(), "ffffff");
+        assert_eq!(color("\x1b[31m\x1b[39m"), None);
+    }
+
+    #[test]
+    fn indexed_colors() {
+        assert_eq!(color("\x1b[38;5;9m").unwrap(), "ff0000");
+        assert_eq!(color("\x1b[38;5;196m").unwrap(), "ff0000");
+        assert_eq!(color("\x1b[38;5;16m").unwrap(), "000000");
+        assert_eq!(color("\x1b[38;5;232m").unwrap(), "080808");
+        assert_eq!(color("\x1b[38;5;255m").unwrap(), "eeeeee");
+    }
+
+    #[test]
+    fn truecolor() {
+        assert_eq!(color("\x1b[38;2;1;2;255m").unwrap(), "0102ff");
+        assert_eq!(color("\x1b[1;38;2;16;32;48;3m").unwrap(), "102030");
+        assert!(sgr("\x1b[1;38;2;16;32;48;3m").italic);
+    }
+
+    #[test]
+    fn background_colors_are_dropped() {
+        assert_eq!(color("\x1b[31;48;5;21m").unwrap(), "cd0000");
+        assert_eq!(color("\x1b[48;2;1;2;3;1m"), None);
+        assert!(sgr("\x1b[48;2;1;2;3;1m").bold);
+    }
+
+    #[test]
+    fn out_of_range_colors_are_ignored() {
+        assert_eq!(color("\x1b[38;5;300m"), None);
+        assert_eq!(color("\x1b[32m\x1b[38;5;300m").unwrap(), "00cd00");
+        assert_eq!(color("\x1b[32m\x1b[38;2;1;256;3m").unwrap(), "00cd00");
+        assert_eq!(color("\x1b[38;5;99999999999m"), None);
+        // The parameters of the ignored color are not read as attributes
+        assert!(!sgr("\x1b[38;5;301m").bold);
+    }
+
+    #[test]
+    fn malformed_sequences() {
+        // Missing channels and unknown color kinds
+        assert_eq!(color("\x1b[38;2;1m"), None);
+        assert_eq!(color("\x1b[38;7;1m"), None);
+        assert_eq!(color("\x1b[38m"), None);
+        // Non-numeric parameters are unknown, not a reset
+        assert!(sgr("\x1b[1m\x1b[xm").bold);
+        // Unterminated and non-SGR sequences write no text and change no attribute
+        assert_eq!(convert(b"a\x1b[1"), "<pre style=\"margin-bottom:-0.5em\">a</pre>\n");
+        assert_eq!(convert(b"a\x1b[2Kb\x1b]0;title\x07c\x1b(Bd"),
+                   "<pre style=\"margin-bottom:-0.5em\">abcd</pre>\n");
+        assert!(!sgr("\x1b[1K").faint);
+    }
+
+    #[test]
+    fn colored_level_names_take_the_palette() {
+        let palette = ColorPalette::default();
+        assert_eq!(convert(b"\x1b[31mERRO\x1b[0m q"),
+                   format!("<pre style=\"margin-bottom:-0.5em\"><span style=\"color:#{};\">ERRO</span> \
+                            q</pre>\n",
+                           palette.level_to_color(Level::Error)));
+    }
+}
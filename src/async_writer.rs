@@ -0,0 +1,318 @@
+use std::io;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use slog::{BorrowedKeyValue, Drain, Level, OwnedKeyValueList, Record, RecordStatic};
+use slog_stream::{Decorator, Format as StreamFormat};
+
+use Format;
+use decorator::{DocumentDecorator, MarkupDecorator};
+
+/// Handling of records logged while the channel to the writer thread is full
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Block the logging thread until there is room (default)
+    Block,
+    /// Drop the record
+    Drop,
+    /// Drop the record, and log the number of dropped records once there is room again
+    DropCounted,
+}
+
+/// Static part of the record reporting dropped records
+static DROPPED_RECORD: RecordStatic<'static> = RecordStatic {
+    level: Level::Warning,
+    file: file!(),
+    line: line!(),
+    column: 0,
+    function: "",
+    module: module_path!(),
+    target: module_path!(),
+};
+
+/// Drain formatting records on the logging thread and writing them on a background thread
+///
+/// The writer thread writes the document prologue before the first record, and the
+/// epilogue once the drain is dropped or shut down by its `AsyncGuard`.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate slog;
+/// # extern crate slog_html;
+/// #
+/// # use slog::DrainExt;
+/// #
+/// # use std::fs::File;
+/// #
+/// # fn main() {
+/// let file = File::create("target/async.html").unwrap();
+/// let (drain, _guard) = slog_html::async_writer(file, slog_html::default())
+///     .capacity(256)
+///     .overflow(slog_html::Overflow::DropCounted)
+///     .build_with_guard()
+///     .unwrap();
+///
+/// let log = slog::Logger::root(drain.fuse(), o!("version" => env!("CARGO_PKG_VERSION")));
+/// info!(log, "started");
+/// # }
+/// ```
+pub struct AsyncWriter<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    shared: Arc<Shared<D>>,
+}
+
+/// Shuts down an `AsyncWriter` when dropped, writing the document epilogue
+///
+/// Records logged after shutdown fail with `io::ErrorKind::BrokenPipe`. Use `finish` to
+/// handle a failure of the writer thread, which is otherwise reported on stderr.
+pub struct AsyncGuard<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    shared: Arc<Shared<D>>,
+}
+
+struct Shared<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    format: Format<D>,
+    overflow: Overflow,
+    dropped: AtomicUsize,
+    sender: RwLock<Option<SyncSender<Vec<u8>>>>,
+    worker: Mutex<Option<JoinHandle<io::Result<()>>>>,
+}
+
+/// `AsyncWriter` builder
+pub struct AsyncWriterBuilder<W, D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    io: W,
+    format: Format<D>,
+    capacity: usize,
+    overflow: Overflow,
+    document: bool,
+}
+
+impl<W, D> AsyncWriterBuilder<W, D>
+    where W: io::Write + Send + 'static,
+          D: DocumentDecorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    /// New `AsyncWriterBuilder` writing records of `format` to `io`
+    pub fn new(io: W, format: Format<D>) -> Self {
+        AsyncWriterBuilder {
+            io,
+            format,
+            capacity: 1024,
+            overflow: Overflow::Block,
+            document: true,
+        }
+    }
+
+    /// Number of records queued for the writer thread (default: 1024)
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Handling of records logged while the queue is full (default: `Overflow::Block`)
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Write the document prologue and epilogue around the records (default)
+    pub fn document(mut self, enabled: bool) -> Self {
+        self.document = enabled;
+        self
+    }
+
+    /// Spawn the writer thread, shut down once the drain is dropped
+    pub fn build(self) -> io::Result<AsyncWriter<D>> {
+        let shared = self.spawn()?;
+        Ok(AsyncWriter { shared })
+    }
+
+    /// Spawn the writer thread, returning a guard shutting it down when dropped
+    ///
+    /// Use this if the drain may outlive the point where its output must be complete,
+    /// e.g. when the logger is kept in a global.
+    pub fn build_with_guard(self) -> io::Result<(AsyncWriter<D>, AsyncGuard<D>)> {
+        let shared = self.spawn()?;
+        Ok((AsyncWriter { shared: shared.clone() }, AsyncGuard { shared }))
+    }
+
+    fn spawn(self) -> io::Result<Arc<Shared<D>>> {
+        let mut prologue = Vec::new();
+        let mut epilogue = Vec::new();
+        if self.document {
+            self.format.write_prologue(&mut prologue)?;
+            self.format.write_epilogue(&mut epilogue)?;
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let io = self.io;
+        let worker = (thread::Builder::new()
+            .name("slog-html".to_owned())
+            .spawn(move || write_records(io, &receiver, &prologue, &epilogue)))?;
+
+        Ok(Arc::new(Shared {
+            format: self.format,
+            overflow: self.overflow,
+            dropped: AtomicUsize::new(0),
+            sender: RwLock::new(Some(sender)),
+            worker: Mutex::new(Some(worker)),
+        }))
+    }
+}
+
+/// Write everything received to `io`, flushing whenever the queue runs empty
+fn write_records<W: io::Write>(mut io: W,
+                               receiver: &Receiver<Vec<u8>>,
+                               prologue: &[u8],
+                               epilogue: &[u8])
+                               -> io::Result<()> {
+    io.write_all(prologue)?;
+    loop {
+        let buf = match receiver.try_recv() {
+            Ok(buf) => buf,
+            Err(TryRecvError::Empty) => {
+                io.flush()?;
+                match receiver.recv() {
+                    Ok(buf) => buf,
+                    Err(_) => break,
+                }
+            }
+            Err(TryRecvError::Disconnected) => break,
+        };
+        io.write_all(&buf)?;
+    }
+    io.write_all(epilogue)?;
+    io.flush()
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "html writer thread stopped")
+}
+
+impl<D> Shared<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    /// Format the record reporting `count` dropped records
+    fn format_dropped(&self, io: &mut dyn io::Write, count: usize) -> io::Result<()> {
+        let values: [BorrowedKeyValue; 1] = [("count", &count)];
+        let record = Record::new(&DROPPED_RECORD, format_args!("records dropped"), &values);
+        // The only way to make an empty list of logger values in slog 1.x
+        #[allow(deprecated)]
+        let logger_values = OwnedKeyValueList::root(None);
+        self.format.format(io, &record, &logger_values)
+    }
+
+    /// Queue a formatted record, returning whether it was dropped
+    fn send(&self, buf: Vec<u8>) -> io::Result<bool> {
+        let sender = self.sender.read().expect("failed to lock html writer sender");
+        let sender = match *sender {
+            Some(ref sender) => sender,
+            None => return Err(stopped()),
+        };
+        if self.overflow == Overflow::Block {
+            return sender.send(buf).map(|()| false).map_err(|_| stopped());
+        }
+        match sender.try_send(buf) {
+            Ok(()) => Ok(false),
+            Err(TrySendError::Full(_)) => Ok(true),
+            Err(TrySendError::Disconnected(_)) => Err(stopped()),
+        }
+    }
+
+    /// Close the queue and wait for the writer thread to write the epilogue
+    ///
+    /// Returns the error that stopped the writer thread, if any. Once shut down, returns
+    /// `Ok(())`.
+    fn shutdown(&self) -> io::Result<()> {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            let mut buf = Vec::new();
+            if self.format_dropped(&mut buf, dropped).is_ok() {
+                let sender = self.sender.read().expect("failed to lock html writer sender");
+                if let Some(ref sender) = *sender {
+                    let _ = sender.send(buf);
+                }
+            }
+        }
+
+        self.sender.write().expect("failed to lock html writer sender").take();
+        let worker = self.worker.lock().expect("failed to lock html writer thread").take();
+        match worker.map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("html writer thread panicked")),
+            None => Ok(()),
+        }
+    }
+
+    /// Shut down, reporting a failure of the writer thread on stderr as it can not be
+    /// returned
+    fn shutdown_on_drop(&self) {
+        if let Err(err) = self.shutdown() {
+            eprintln!("slog-html: failed to write html log: {}", err);
+        }
+    }
+}
+
+impl<D> AsyncGuard<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    /// Shut down the writer thread, returning the error that stopped it, if any
+    ///
+    /// That is a failure to write or flush, or a panic of the writer. Records logged after
+    /// the thread stopped only fail with `io::ErrorKind::BrokenPipe`.
+    pub fn finish(self) -> io::Result<()> {
+        self.shared.shutdown()
+    }
+}
+
+impl<D> Drain for AsyncWriter<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    type Error = io::Error;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKeyValueList) -> io::Result<()> {
+        let shared = &self.shared;
+        let mut buf = Vec::new();
+        let dropped = if shared.overflow == Overflow::DropCounted {
+            shared.dropped.swap(0, Ordering::Relaxed)
+        } else {
+            0
+        };
+        if dropped > 0 {
+            shared.format_dropped(&mut buf, dropped)?;
+        }
+        shared.format.format(&mut buf, record, logger_values)?;
+
+        if shared.send(buf)? && shared.overflow == Overflow::DropCounted {
+            shared.dropped.fetch_add(dropped + 1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+impl<D: Decorator> Drop for AsyncWriter<D>
+    where D::RecordDecorator: MarkupDecorator
+{
+    fn drop(&mut self) {
+        self.shared.shutdown_on_drop();
+    }
+}
+
+impl<D: Decorator> Drop for AsyncGuard<D>
+    where D::RecordDecorator: MarkupDecorator
+{
+    fn drop(&mut self) {
+        self.shared.shutdown_on_drop();
+    }
+}
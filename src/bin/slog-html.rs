@@ -0,0 +1,124 @@
+//! Command line tools for html logs
+
+extern crate slog_html;
+
+use std::{env, fs, io, process};
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+
+const USAGE: &str = "Usage: slog-html <command> [options]
+
+Commands:
+    ansi [INPUT] [-o OUTPUT]    Convert a terminal log with ANSI colors to html
+    json [INPUT] [-o OUTPUT]    Convert an html log to JSON lines
+    merge INPUT... [-o OUTPUT]  Merge html logs into one, ordered by timestamp
+
+INPUT defaults to standard input and OUTPUT to standard output. Merged logs are
+labeled with their file names, or LABEL for inputs given as LABEL=INPUT.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("ansi") => ansi(&args[1..]),
+        Some("json") => json(&args[1..]),
+        Some("merge") => merge(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("slog-html: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Input and output files of a command
+struct Files {
+    inputs: Vec<String>,
+    output: Option<String>,
+}
+
+impl Files {
+    /// Parse the arguments of a command taking a single input, or several if `multiple`
+    fn parse(args: &[String], multiple: bool) -> io::Result<Self> {
+        let mut files = Files {
+            inputs: Vec::new(),
+            output: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    let output = args.next().ok_or_else(|| usage_error("missing OUTPUT"))?;
+                    files.output = Some(output.clone());
+                }
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(usage_error(&format!("unknown option {}", arg)));
+                }
+                _ if multiple || files.inputs.is_empty() => files.inputs.push(arg.clone()),
+                _ => return Err(usage_error(&format!("unexpected argument {}", arg))),
+            }
+        }
+        Ok(files)
+    }
+
+    fn input(&self) -> io::Result<Box<dyn BufRead>> {
+        match self.inputs.first().map(String::as_str) {
+            None | Some("-") => Ok(Box::new(io::BufReader::new(io::stdin()))),
+            Some(path) => Ok(Box::new(io::BufReader::new(fs::File::open(path)?))),
+        }
+    }
+
+    fn output(&self) -> io::Result<Box<dyn io::Write>> {
+        match self.output.as_deref() {
+            None | Some("-") => Ok(Box::new(io::BufWriter::new(io::stdout()))),
+            Some(path) => Ok(Box::new(io::BufWriter::new(fs::File::create(path)?))),
+        }
+    }
+}
+
+fn usage_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{}\n\n{}", message, USAGE))
+}
+
+fn ansi(args: &[String]) -> io::Result<()> {
+    let files = Files::parse(args, false)?;
+    slog_html::AnsiConverter::new().convert(files.input()?, files.output()?)
+}
+
+fn json(args: &[String]) -> io::Result<()> {
+    let files = Files::parse(args, false)?;
+    let mut html = String::new();
+    files.input()?.read_to_string(&mut html)?;
+    let mut output = files.output()?;
+    for record in slog_html::parse(&html)? {
+        record.write_json(&mut output)?;
+    }
+    output.flush()
+}
+
+fn merge(args: &[String]) -> io::Result<()> {
+    let files = Files::parse(args, true)?;
+    if files.inputs.is_empty() {
+        return Err(usage_error("missing INPUT"));
+    }
+    let mut merger = slog_html::Merger::new();
+    for input in &files.inputs {
+        let (label, path) = match input.find('=') {
+            Some(i) => (input[..i].to_owned(), &input[i + 1..]),
+            None => {
+                let stem = Path::new(input).file_stem().map(|stem| stem.to_string_lossy());
+                (stem.map_or_else(|| input.clone(), |stem| stem.into_owned()), input.as_str())
+            }
+        };
+        let mut html = String::new();
+        fs::File::open(path)?.read_to_string(&mut html)?;
+        merger = merger.source(label, html);
+    }
+    merger.merge(files.output()?)
+}
@@ -0,0 +1,27 @@
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+/// Capacity above which a buffer is released instead of kept for reuse
+///
+/// A single huge record must not keep its memory allocated for the lifetime of the thread.
+const MAX_RETAINED_CAPACITY: usize = 64 * 1024;
+
+/// Run `f` with a cleared thread-local buffer
+///
+/// Falls back to a new buffer if the thread-local one is already in use, e.g. when
+/// formatting a value logs another record on the same thread.
+pub fn with_buffer<F, T>(key: &'static LocalKey<RefCell<Vec<u8>>>, f: F) -> T
+    where F: FnOnce(&mut Vec<u8>) -> T
+{
+    key.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            let result = f(&mut buffer);
+            if buffer.capacity() > MAX_RETAINED_CAPACITY {
+                *buffer = Vec::new();
+            }
+            result
+        }
+        Err(_) => f(&mut Vec::new()),
+    })
+}
@@ -1,6 +1,7 @@
 use slog::Level;
 
-/// Hexadecimal color codes
+/// Hexadecimal color codes of 3 or 6 digits, without `#`
+#[derive(Clone, Copy)]
 pub struct ColorPalette {
     /// Color for critical messages
     pub critical: &'static str,
@@ -38,21 +39,21 @@ impl ColorPalette {
 
 impl Default for ColorPalette {
     /// ```text
-    /// critical: "ff0000"
-    /// error: "ff5500"
-    /// warning: "ffaa00"
-    /// info: "55aa00"
+    /// critical: "d70000"
+    /// error: "c84400"
+    /// warning: "996600"
+    /// info: "3f7f00"
     /// debug: "55557f"
-    /// trace: "aaaa7f"
+    /// trace: "75755a"
     /// ```
     fn default() -> Self {
         ColorPalette {
-            critical: "ff0000",
-            error: "ff5500",
-            warning: "ffaa00",
-            info: "55aa00",
+            critical: "d70000",
+            error: "c84400",
+            warning: "996600",
+            info: "3f7f00",
             debug: "55557f",
-            trace: "aaaa7f",
+            trace: "75755a",
         }
     }
 }
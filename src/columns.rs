@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Number of keys seen once that are remembered
+///
+/// A key is only given a column once it is seen again, in a later record.
+const MAX_SEEN: usize = 256;
+
+/// Number of columns, after which repeated keys are no longer given one
+const MAX_COLUMNS: usize = 16;
+
+/// Columns of keys appearing in more than one record, in the order they first repeated
+pub struct Columns {
+    state: Mutex<ColumnsState>,
+}
+
+struct ColumnsState {
+    /// Key of each column, with the width of its widest pair
+    columns: Vec<(String, usize)>,
+    /// Keys seen in a single record so far, most recently seen first
+    seen: VecDeque<String>,
+}
+
+/// Place of the pairs of a record
+pub struct Layout {
+    /// Index of the pair in each column and the column's width, or `None` if the record
+    /// has no pair with its key
+    pub columns: Vec<(Option<usize>, usize)>,
+    /// Indices of the pairs without a column, in order
+    pub others: Vec<usize>,
+}
+
+impl Columns {
+    pub fn new() -> Self {
+        Columns {
+            state: Mutex::new(ColumnsState {
+                columns: Vec::new(),
+                seen: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Lay out the pairs with `keys`, giving a column to the keys seen before
+    ///
+    /// A key appearing more than once in the record takes its column with its first pair.
+    pub fn layout(&self, keys: &[&str]) -> Layout {
+        let mut state = self.state.lock().expect("failed to lock columns");
+        let mut layout = Layout {
+            columns: state.columns.iter().map(|&(_, width)| (None, width)).collect(),
+            others: Vec::new(),
+        };
+        for (i, &key) in keys.iter().enumerate() {
+            let column = match state.columns.iter().position(|(column, _)| column == key) {
+                Some(column) => Some(column),
+                None => state.promote(key),
+            };
+            match column {
+                Some(column) if column == layout.columns.len() => {
+                    layout.columns.push((Some(i), 0));
+                }
+                Some(column) if layout.columns[column].0.is_none() => {
+                    layout.columns[column].0 = Some(i);
+                }
+                _ => layout.others.push(i),
+            }
+        }
+        layout
+    }
+
+    /// Widen `column` to fit a pair of `width` columns, returning the width of the column
+    pub fn widen(&self, column: usize, width: usize) -> usize {
+        let mut state = self.state.lock().expect("failed to lock columns");
+        let column = &mut state.columns[column].1;
+        *column = (*column).max(width);
+        *column
+    }
+}
+
+impl ColumnsState {
+    /// Give `key` a new column if it was seen before, or else remember it
+    fn promote(&mut self, key: &str) -> Option<usize> {
+        match self.seen.iter().position(|seen| seen == key) {
+            Some(i) if self.columns.len() < MAX_COLUMNS => {
+                self.seen.remove(i);
+                self.columns.push((key.to_owned(), 0));
+                Some(self.columns.len() - 1)
+            }
+            Some(_) => None,
+            None => {
+                self.seen.push_front(key.to_owned());
+                self.seen.truncate(MAX_SEEN);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use slog::OwnedKeyValueList;
+
+/// Number of nodes after which the cache is cleared
+///
+/// Every cached node keeps its logger values alive, so loggers created and dropped in a
+/// loop must not grow the cache without bounds.
+const MAX_NODES: usize = 1024;
+
+/// Formatted key-value pairs of a logger node
+#[derive(PartialEq)]
+pub struct NodeValues {
+    pub pairs: Vec<(&'static str, Vec<u8>)>,
+}
+
+impl NodeValues {
+    /// Whether the same key-value pair is part of these values
+    pub fn contains(&self, key: &str, formatted: &[u8]) -> bool {
+        self.pairs.iter().any(|(k, f)| *k == key && &f[..] == formatted)
+    }
+}
+
+/// Formatted values of every node of a logger context, starting at the root
+pub type ValueStack = Vec<Arc<NodeValues>>;
+
+/// Number of leading levels two value stacks have in common
+pub fn shared_levels(a: &ValueStack, b: &ValueStack) -> usize {
+    a.iter()
+        .zip(b.iter())
+        .take_while(|&(a, b)| Arc::ptr_eq(a, b) || a == b)
+        .count()
+}
+
+/// Serialized logger values, cached per `OwnedKeyValueList` node
+pub struct ContextCache {
+    nodes: RwLock<HashMap<usize, CachedNode>>,
+}
+
+struct CachedNode {
+    /// Keeps the node alive, so that its id is not reused by another node
+    _node: OwnedKeyValueList,
+    serialized: Arc<NodeValues>,
+}
+
+impl ContextCache {
+    pub fn new() -> Self {
+        ContextCache { nodes: RwLock::new(HashMap::new()) }
+    }
+
+    /// Get the serialized values of `node`, calling `serialize` on a cache miss
+    pub fn get<F>(&self, node: &OwnedKeyValueList, serialize: F) -> io::Result<Arc<NodeValues>>
+        where F: FnOnce() -> io::Result<NodeValues>
+    {
+        // Cached nodes are kept alive, so their ids cannot be taken by new nodes
+        #[allow(deprecated)]
+        let id = node.id();
+        {
+            let nodes = self.nodes.read().expect("failed to lock context cache");
+            if let Some(cached) = nodes.get(&id) {
+                return Ok(cached.serialized.clone());
+            }
+        }
+
+        let serialized = Arc::new(serialize()?);
+        let mut nodes = self.nodes.write().expect("failed to lock context cache");
+        if nodes.len() >= MAX_NODES {
+            nodes.clear();
+        }
+        let cached = nodes.entry(id).or_insert_with(|| {
+            CachedNode {
+                _node: node.clone(),
+                serialized,
+            }
+        });
+        Ok(cached.serialized.clone())
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+/// Minimum contrast ratio of normal text against its background, after WCAG 2 level AA
+pub const MIN_CONTRAST: f64 = 4.5;
+
+/// Color of a style with too little contrast against the background
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContrastWarning {
+    slot: &'static str,
+    color: String,
+    background: String,
+    ratio: f64,
+}
+
+impl ContrastWarning {
+    /// What the color is used for, like `"warning level"` or `"key"`
+    pub fn slot(&self) -> &'static str {
+        self.slot
+    }
+
+    /// The color, as a hexadecimal color code
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    /// Contrast ratio of the color against the background, below `MIN_CONTRAST`
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+}
+
+impl fmt::Display for ContrastWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "color #{} of {} has a contrast of {:.2}:1 against #{}, below {}:1",
+               self.color,
+               self.slot,
+               self.ratio,
+               self.background,
+               MIN_CONTRAST)
+    }
+}
+
+/// Check the contrast of `color` against `background`
+///
+/// Colors that are not hexadecimal color codes are not checked.
+pub fn check(slot: &'static str, color: &str, background: &str) -> Option<ContrastWarning> {
+    match ratio(color, background) {
+        Some(ratio) if ratio < MIN_CONTRAST => {
+            Some(ContrastWarning {
+                slot,
+                color: color.to_owned(),
+                background: background.to_owned(),
+                ratio,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Contrast ratio of two hexadecimal color codes, from 1 to 21
+pub fn ratio(color: &str, background: &str) -> Option<f64> {
+    let (a, b) = (luminance(color)?, luminance(background)?);
+    Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+}
+
+/// `color` darkened or lightened, whichever suits `background`, until its contrast is at
+/// least `MIN_CONTRAST`
+///
+/// Colors with enough contrast and colors that are not hexadecimal color codes are kept.
+pub fn adjust(color: &str, background: &str) -> String {
+    let enough = |color: &str| ratio(color, background).is_none_or(|ratio| ratio >= MIN_CONTRAST);
+    let (rgb, background_luminance) = match (parse(color), luminance(background)) {
+        (Some(rgb), Some(luminance)) if !enough(color) => (rgb, luminance),
+        _ => return color.to_owned(),
+    };
+    // Black has more contrast than white against backgrounds lighter than this
+    let target = if background_luminance > 0.179 { 0.0 } else { 255.0 };
+    let mut adjusted = String::new();
+    for step in 1..=20 {
+        let mix = step as f64 / 20.0;
+        let channel = |c: u8| (c as f64 + (target - c as f64) * mix).round() as u8;
+        adjusted = format!("{:02x}{:02x}{:02x}",
+                           channel(rgb[0]),
+                           channel(rgb[1]),
+                           channel(rgb[2]));
+        if enough(&adjusted) {
+            break;
+        }
+    }
+    adjusted
+}
+
+/// Relative luminance of a hexadecimal color code
+fn luminance(color: &str) -> Option<f64> {
+    let rgb = parse(color)?;
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Some(0.2126 * linear(rgb[0]) + 0.7152 * linear(rgb[1]) + 0.0722 * linear(rgb[2]))
+}
+
+/// Channels of a hexadecimal color code of 3 or 6 digits, with or without `#`
+fn parse(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim_start_matches('#');
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok();
+    match hex.len() {
+        // `#abc` is short for `#aabbcc`
+        3 => Some([channel(0, 1)? * 17, channel(1, 1)? * 17, channel(2, 1)? * 17]),
+        6 => Some([channel(0, 2)?, channel(1, 2)?, channel(2, 2)?]),
+        _ => None,
+    }
+}
@@ -0,0 +1,51 @@
+/// Stylesheet of html output under a strict Content Security Policy
+///
+/// Without `style` attributes, elements are styled by the classes of a stylesheet, which
+/// the document either has inline in a `<style>` element tagged with a nonce, or loads from
+/// a static file. The output has no scripts.
+///
+/// ```
+/// # extern crate slog_html;
+/// # use std::fs::File;
+/// # fn main() {
+/// let builder = slog_html::new().csp(slog_html::Csp::Stylesheet("log.css".to_owned()));
+/// assert_eq!(builder.csp_header().unwrap(), "default-src 'none'; style-src 'self'");
+///
+/// let format = builder.build().unwrap();
+/// format.write_stylesheet(&mut File::create("target/log.css").unwrap()).unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Csp {
+    /// Inline stylesheet in a `<style>` element with this nonce
+    ///
+    /// The nonce must be a new base64 value for every response serving the document.
+    Nonce(String),
+    /// Stylesheet linked from this URL, written with `Format::write_stylesheet`
+    Stylesheet(String),
+}
+
+impl Csp {
+    /// Value of the `Content-Security-Policy` header allowing the document and nothing else
+    ///
+    /// A relative stylesheet URL is allowed as `'self'`, an absolute one by its origin.
+    pub fn header(&self) -> String {
+        let source = match *self {
+            Csp::Nonce(ref nonce) => format!("'nonce-{}'", nonce),
+            Csp::Stylesheet(ref url) => origin(url).unwrap_or("'self'").to_owned(),
+        };
+        format!("default-src 'none'; style-src {}", source)
+    }
+}
+
+/// Scheme, host and port of an absolute URL
+fn origin(url: &str) -> Option<&str> {
+    let scheme_end = url.find("://").filter(|&i| {
+        url[..i].chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    scheme_end.map(|i| {
+        let rest = &url[i + 3..];
+        let host_len = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        &url[..i + 3 + host_len]
+    })
+}
@@ -0,0 +1,229 @@
+use std::error;
+use std::fmt;
+
+/// Properties custom CSS may set
+///
+/// Layout properties that could move or hide other lines, like `position` or `display`,
+/// are left out, as are properties taking images.
+const PROPERTIES: &[&str] = &["background-color",
+                              "border",
+                              "border-bottom",
+                              "border-color",
+                              "border-left",
+                              "border-radius",
+                              "border-right",
+                              "border-style",
+                              "border-top",
+                              "border-width",
+                              "color",
+                              "font-family",
+                              "font-size",
+                              "font-style",
+                              "font-variant",
+                              "font-weight",
+                              "letter-spacing",
+                              "line-height",
+                              "margin",
+                              "margin-bottom",
+                              "margin-left",
+                              "margin-right",
+                              "margin-top",
+                              "opacity",
+                              "outline",
+                              "padding",
+                              "padding-bottom",
+                              "padding-left",
+                              "padding-right",
+                              "padding-top",
+                              "text-decoration",
+                              "text-decoration-color",
+                              "text-decoration-line",
+                              "text-decoration-style",
+                              "text-shadow",
+                              "text-transform",
+                              "vertical-align",
+                              "white-space"];
+
+/// Functions values may call
+const FUNCTIONS: &[&str] = &["calc", "hsl", "hsla", "rgb", "rgba"];
+
+/// Error in the custom CSS of a `Style`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CssError {
+    css: String,
+    reason: String,
+}
+
+impl CssError {
+    fn new(css: &str, reason: String) -> Self {
+        CssError {
+            css: css.to_owned(),
+            reason,
+        }
+    }
+
+    /// The invalid CSS
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+}
+
+impl fmt::Display for CssError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid custom CSS {:?}: {}", self.css, self.reason)
+    }
+}
+
+impl error::Error for CssError {
+    fn description(&self) -> &str {
+        "invalid custom CSS"
+    }
+}
+
+/// Parse custom CSS into declarations, checked against the allowed properties
+///
+/// Property names are lowercase. Values may only contain numbers, identifiers, colors,
+/// quoted font names and calls of a few functions, like `rgb()`. Comments, escapes, at-rules
+/// and braces are rejected.
+pub fn parse(css: &str) -> Result<Vec<(String, String)>, CssError> {
+    let mut declarations = Vec::new();
+    for declaration in split_declarations(css)? {
+        let declaration = declaration.trim();
+        if declaration.is_empty() {
+            continue;
+        }
+        let (property, value) = match declaration.find(':') {
+            Some(i) => (&declaration[..i], &declaration[i + 1..]),
+            None => {
+                return Err(CssError::new(css, format!("{:?} is not a declaration", declaration)))
+            }
+        };
+        let property = property.trim().to_ascii_lowercase();
+        if !PROPERTIES.contains(&property.as_str()) {
+            return Err(CssError::new(css, format!("property {:?} is not allowed", property)));
+        }
+        let value = value.trim();
+        if let Err(reason) = check_value(value) {
+            return Err(CssError::new(css, format!("value of {}: {}", property, reason)));
+        }
+        declarations.push((property, value.to_owned()));
+    }
+    Ok(declarations)
+}
+
+/// Parse custom CSS, returning its declarations normalized
+///
+/// The declarations are safe inside a `<style>` element as they are, and in a `style`
+/// attribute once escaped.
+pub fn sanitize(css: &str) -> Result<String, CssError> {
+    let mut sanitized = String::new();
+    for (property, value) in parse(css)? {
+        sanitized.push_str(&property);
+        sanitized.push(':');
+        sanitized.push_str(&value);
+        sanitized.push(';');
+    }
+    Ok(sanitized)
+}
+
+/// Check that `color` is a hexadecimal color code of 3 or 6 digits, without `#`
+///
+/// Colors are written into `style` attributes and stylesheets as they are, so anything else
+/// could add declarations or rules of its own.
+pub fn check_color(color: &str) -> Result<(), CssError> {
+    if (color.len() == 3 || color.len() == 6) && color.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(CssError::new(color, "not a hexadecimal color code of 3 or 6 digits".to_owned()))
+    }
+}
+
+/// Check that `name` can be used as a class name in selectors without escapes
+pub fn check_identifier(name: &str) -> Result<(), CssError> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(CssError::new(name,
+                          "not a CSS identifier made of ASCII letters, digits, - and _"
+                              .to_owned()))
+    }
+}
+
+/// Split CSS at the semicolons outside of quotes and parentheses
+fn split_declarations(css: &str) -> Result<Vec<&str>, CssError> {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut depth = 0usize;
+    for (i, c) in css.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth = (depth.checked_sub(1)
+                    .ok_or_else(|| CssError::new(css, "unbalanced parentheses".to_owned())))?;
+            }
+            (None, ';') if depth == 0 => {
+                declarations.push(&css[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        return Err(CssError::new(css, "unterminated string".to_owned()));
+    }
+    if depth > 0 {
+        return Err(CssError::new(css, "unbalanced parentheses".to_owned()));
+    }
+    declarations.push(&css[start..]);
+    Ok(declarations)
+}
+
+/// Check that a value only has harmless tokens
+fn check_value(value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Err("value is empty".to_owned());
+    }
+    if value.contains("/*") {
+        return Err("comments are not allowed".to_owned());
+    }
+    let mut quote = None;
+    let mut word = String::new();
+    for c in value.chars() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else if !(c.is_alphanumeric() || " -_".contains(c)) {
+                return Err(format!("{:?} is not allowed in a string", c));
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => {
+                let function = word.to_ascii_lowercase();
+                if !FUNCTIONS.contains(&function.as_str()) {
+                    return Err(format!("function {:?} is not allowed", function));
+                }
+            }
+            c if c.is_alphanumeric() || "-_".contains(c) => {
+                word.push(c);
+                continue;
+            }
+            c if c.is_whitespace() || "#%.,)+*/!".contains(c) => {}
+            c => return Err(format!("{:?} is not allowed", c)),
+        }
+        word.clear();
+    }
+    Ok(())
+}
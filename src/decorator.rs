@@ -1,24 +1,390 @@
+use buffer::with_buffer;
 use color_palette::ColorPalette;
+use contrast;
+use csp::Csp;
+use css;
+use escape::{Escape, escape};
+use linkify::Linkifier;
+use width;
+use structured::{StructuredValue, TreeNode};
 use style::{Style, StyleTable};
 
-use std::io;
+use std::{cmp, io};
+use std::io::Write;
+use std::cell::RefCell;
+use std::sync::Arc;
 
-use slog::Record;
+use slog::{Level, Record};
 use slog_stream::{Decorator, RecordDecorator};
 
-/// Html decorator
+/// Per-record decorator with hooks for output that `RecordDecorator` has no method for
+///
+/// All methods have a plain text fallback, so any `RecordDecorator` can opt in with an
+/// empty `impl`.
+pub trait MarkupDecorator: RecordDecorator {
+    /// Format a structured value
+    ///
+    /// Falls back to the one-line summary formatted as a value.
+    fn fmt_structured_value(&self,
+                            io: &mut dyn io::Write,
+                            value: &StructuredValue)
+                            -> io::Result<()> {
+        let summary = value.summary();
+        self.fmt_value(io, &|io| write!(io, "{}", summary))
+    }
+
+    /// Format a time column
+    ///
+    /// `gap` is set for times between records above the configured threshold. Falls back
+    /// to the timestamp format.
+    fn fmt_duration(&self,
+                    io: &mut dyn io::Write,
+                    f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>,
+                    gap: bool)
+                    -> io::Result<()> {
+        let _ = gap;
+        self.fmt_timestamp(io, f)
+    }
+
+    /// Format output that did not change since it was printed last
+    ///
+    /// `f` writes formatted output, not text. Falls back to leaving it unchanged.
+    fn fmt_unchanged(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        f(io)
+    }
+
+    /// Format the marker of a logger context's lane
+    ///
+    /// `parent` is set on the lines of a new lane, to the lane of the context it shares the
+    /// most levels with, whose headers are not printed again. Falls back to the lane number.
+    fn fmt_lane(&self,
+                io: &mut dyn io::Write,
+                lane: usize,
+                parent: Option<usize>)
+                -> io::Result<()> {
+        let _ = parent;
+        self.fmt_separator(io, &|io| write!(io, "{:>2} ", lane))
+    }
+
+    /// Format a line of output: a record, or a context header in compact mode
+    ///
+    /// `f` writes formatted output, not text. Falls back to ending it with a newline.
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        f(io)?;
+        io.write_all(b"\n")
+    }
+
+    /// Format the indentation of a line in compact mode
+    ///
+    /// Falls back to two spaces per level.
+    fn fmt_indent(&self, io: &mut dyn io::Write, indent: usize) -> io::Result<()> {
+        for _ in 0..indent {
+            io.write_all(b"  ")?;
+        }
+        Ok(())
+    }
+
+    /// Format the guide lines in front of a line in compact mode
+    ///
+    /// `depth` is the same as the indentation. Falls back to vertical lines for the
+    /// ancestors, and a branch to the line itself.
+    fn fmt_guides(&self, io: &mut dyn io::Write, depth: usize) -> io::Result<()> {
+        write_guides(io, depth)
+    }
+
+    /// Format the key-value pairs of a line
+    ///
+    /// `f` writes formatted output, not text. `after_msg` is set for the pairs of a record,
+    /// which follow its message. Falls back to a pair separator after the message.
+    fn fmt_pairs(&self,
+                 io: &mut dyn io::Write,
+                 after_msg: bool,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        if after_msg {
+            self.fmt_pair_separator(io)?;
+        }
+        f(io)
+    }
+
+    /// Format padding of `columns` columns, aligning what comes after it
+    ///
+    /// Falls back to spaces.
+    fn fmt_padding(&self, io: &mut dyn io::Write, columns: usize) -> io::Result<()> {
+        write!(io, "{:1$}", "", columns)
+    }
+
+    /// Columns a message takes up as formatted, to pad it to the width of the message column
+    ///
+    /// Falls back to the display width of its widest line.
+    fn msg_width(&self, msg: &str) -> usize {
+        width::text_width(msg)
+    }
+
+    /// Columns a value takes up as formatted, to pad its pair to the width of its column
+    ///
+    /// Falls back to the display width of its widest line.
+    fn value_width(&self, value: &str) -> usize {
+        width::text_width(value)
+    }
+
+    /// Format the separator between two key-value pairs
+    ///
+    /// Falls back to a comma.
+    fn fmt_pair_separator(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        self.fmt_separator(io, &|io| write!(io, ", "))
+    }
+
+    /// Format the separator between a key and its value
+    ///
+    /// Falls back to a colon.
+    fn fmt_key_separator(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        self.fmt_separator(io, &|io| write!(io, ": "))
+    }
+}
+
+/// Adapter giving any `RecordDecorator` the fallbacks of the `MarkupDecorator` hooks
+///
+/// Lines are wrapped in `<pre>` elements, as in the first releases.
+pub struct PlainRecordDecorator<R> {
+    inner: R,
+}
+
+impl<R: RecordDecorator> PlainRecordDecorator<R> {
+    /// New `PlainRecordDecorator` of `inner`
+    pub fn new(inner: R) -> Self {
+        PlainRecordDecorator { inner }
+    }
+}
+
+impl<R: RecordDecorator> RecordDecorator for PlainRecordDecorator<R> {
+    fn fmt_msg(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        self.inner.fmt_msg(io, f)
+    }
+
+    fn fmt_key(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        self.inner.fmt_key(io, f)
+    }
+
+    fn fmt_separator(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        self.inner.fmt_separator(io, f)
+    }
+
+    fn fmt_value(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        self.inner.fmt_value(io, f)
+    }
+
+    fn fmt_timestamp(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        self.inner.fmt_timestamp(io, f)
+    }
+
+    fn fmt_level(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        self.inner.fmt_level(io, f)
+    }
+}
+
+impl<R: RecordDecorator> MarkupDecorator for PlainRecordDecorator<R> {
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        io.write_all(LINE_START.as_bytes())?;
+        f(io)?;
+        io.write_all(LINE_END.as_bytes())
+    }
+}
+
+/// Decorator with hooks for the parts of a document around its records
+///
+/// All methods have a plain text fallback, so any `Decorator` can opt in with an empty
+/// `impl`.
+pub trait DocumentDecorator: Decorator {
+    /// Write the start of a document, before the first record
+    fn write_prologue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        let _ = io;
+        Ok(())
+    }
+
+    /// Write the end of a document, after the last record
+    fn write_epilogue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        let _ = io;
+        Ok(())
+    }
+
+    /// Write a heading between records
+    ///
+    /// Falls back to the text of the heading on a line of its own.
+    fn write_heading(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        f(io)?;
+        io.write_all(b"\n")
+    }
+
+    /// Write the stylesheet of the classes the output is styled by
+    ///
+    /// Falls back to nothing, for output styled inline or not at all.
+    fn write_stylesheet(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        let _ = io;
+        Ok(())
+    }
+}
+
+/// Write guide lines of a tree for a line at `depth`, three columns per level
+pub fn write_guides(io: &mut dyn io::Write, depth: usize) -> io::Result<()> {
+    if depth == 0 {
+        return Ok(());
+    }
+    for _ in 1..depth {
+        io.write_all("\u{2502}  ".as_bytes())?;
+    }
+    io.write_all("\u{251c}\u{2500} ".as_bytes())
+}
+
+/// Handling of the part of a message or value over its maximum length
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// Keep the part in a collapsed element, expanded on click
+    Expand,
+    /// Drop the part, keeping only the marker with its size
+    Drop,
+}
+
+/// Html decorator, writing each line as a `<pre>` element
 pub struct HtmlDecorator {
     color_palette: ColorPalette,
     style: StyleTable,
+    tags: Arc<StyleTags>,
+    csp: Option<Csp>,
+    /// Class of the root element of a fragment
+    fragment: Option<String>,
+    /// Prefix of the classes elements are styled by, instead of `style` attributes
+    class_prefix: Option<Arc<String>>,
+    linkifier: Option<Arc<Linkifier>>,
+    collapse_lines: Option<usize>,
+    max_message_len: Option<usize>,
+    max_value_len: Option<usize>,
+    truncation: Truncation,
 }
 
 impl HtmlDecorator {
+    /// New `HtmlDecorator` coloring levels from `color_palette`
     pub fn new(color_palette: ColorPalette, style: StyleTable) -> Self {
         HtmlDecorator {
-            color_palette: color_palette,
-            style: style,
+            color_palette,
+            style,
+            tags: Arc::new(StyleTags::new(&color_palette, &style)),
+            csp: None,
+            fragment: None,
+            class_prefix: None,
+            linkifier: None,
+            collapse_lines: None,
+            max_message_len: None,
+            max_value_len: None,
+            truncation: Truncation::Expand,
+        }
+    }
+
+    /// Turn URLs and other patterns in messages and values into links
+    pub fn linkifier(mut self, linkifier: Option<Linkifier>) -> Self {
+        self.linkifier = linkifier.map(Arc::new);
+        self
+    }
+
+    /// Style elements by the classes of a stylesheet instead of `style` attributes
+    pub fn csp(mut self, csp: Option<Csp>) -> Self {
+        self.csp = csp;
+        self.restyle()
+    }
+
+    /// Write a fragment in a root element of class `prefix`, instead of a document
+    ///
+    /// Elements are styled by classes starting with `prefix`, and the rules of the
+    /// stylesheet only apply within the root element.
+    pub fn fragment(mut self, prefix: Option<String>) -> Self {
+        self.fragment = prefix;
+        self.restyle()
+    }
+
+    /// Render the tags for inline styles or the classes of the stylesheet
+    fn restyle(mut self) -> Self {
+        let class_prefix = match self.fragment {
+            Some(ref prefix) => Some(format!("{}-", prefix)),
+            None if self.csp.is_some() => Some(CLASS_PREFIX.to_owned()),
+            None => None,
+        };
+        self.tags = Arc::new(match class_prefix {
+            Some(ref prefix) => StyleTags::with_classes(&self.color_palette, &self.style, prefix),
+            None => StyleTags::new(&self.color_palette, &self.style),
+        });
+        self.class_prefix = class_prefix.map(Arc::new);
+        self
+    }
+
+    /// Write the `<style>` or `<link>` element of the stylesheet
+    fn write_stylesheet_element(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        match self.csp {
+            Some(Csp::Stylesheet(ref url)) => {
+                io.write_all(b"<link rel=\"stylesheet\" href=\"")?;
+                Escape(&mut *io).write_all(url.as_bytes())?;
+                io.write_all(b"\">\n")
+            }
+            ref csp => {
+                io.write_all(b"<style")?;
+                if let Some(Csp::Nonce(ref nonce)) = *csp {
+                    io.write_all(b" nonce=\"")?;
+                    Escape(&mut *io).write_all(nonce.as_bytes())?;
+                    io.write_all(b"\"")?;
+                }
+                io.write_all(b">\n")?;
+                self.write_stylesheet(io)?;
+                io.write_all(b"</style>\n")
+            }
         }
     }
+
+    /// Collapse multi-line messages and values after `lines` lines
+    pub fn collapse_lines(mut self, lines: Option<usize>) -> Self {
+        self.collapse_lines = lines;
+        self
+    }
+
+    /// Truncate messages and values longer than the given number of bytes
+    pub fn truncate(mut self,
+                    max_message_len: Option<usize>,
+                    max_value_len: Option<usize>,
+                    truncation: Truncation)
+                    -> Self {
+        self.max_message_len = max_message_len;
+        self.max_value_len = max_value_len;
+        self.truncation = truncation;
+        self
+    }
 }
 
 impl Decorator for HtmlDecorator {
@@ -26,103 +392,723 @@ impl Decorator for HtmlDecorator {
 
     fn decorate(&self, record: &Record) -> HtmlRecordDecorator {
         HtmlRecordDecorator {
-            level_color: self.color_palette.level_to_color(record.level()),
-            style: self.style,
+            level: record.level(),
+            tags: self.tags.clone(),
+            class_prefix: self.class_prefix.clone(),
+            linkifier: self.linkifier.clone(),
+            collapse_lines: self.collapse_lines,
+            max_message_len: self.max_message_len,
+            max_value_len: self.max_value_len,
+            truncation: self.truncation,
+        }
+    }
+}
+
+impl DocumentDecorator for HtmlDecorator {
+    fn write_prologue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        // The stylesheet of a fragment belongs in the head of the page embedding it
+        if let Some(ref prefix) = self.fragment {
+            return writeln!(io, "<div class=\"{}\" role=\"log\">", prefix);
+        }
+        io.write_all(DOCUMENT_HEAD_START.as_bytes())?;
+        if self.class_prefix.is_some() {
+            self.write_stylesheet_element(io)?;
+        }
+        io.write_all(DOCUMENT_HEAD_END.as_bytes())
+    }
+
+    fn write_epilogue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        if self.fragment.is_some() {
+            return io.write_all(b"</div>\n");
+        }
+        io.write_all(DOCUMENT_EPILOGUE.as_bytes())
+    }
+
+    fn write_heading(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        io.write_all(b"<h3>")?;
+        f(&mut Escape(&mut *io))?;
+        io.write_all(b"</h3>\n")
+    }
+
+    fn write_stylesheet(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        let prefix = match self.class_prefix {
+            Some(ref prefix) => prefix,
+            None => return Ok(()),
+        };
+        // Rules of a fragment only match within its root element
+        let scope = match self.fragment {
+            Some(ref root) => format!(".{} ", root),
+            None => String::new(),
+        };
+        for &(class, css) in &FIXED_STYLES {
+            writeln!(io, "{}.{}{}{{{}}}", scope, prefix, class, css)?;
         }
+        for (lane, color) in self.tags.lanes.iter().enumerate() {
+            writeln!(io, "{}.{}lane-{}{{color:#{};}}", scope, prefix, lane, color)?;
+        }
+        writeln!(io, "{}.{}guides{{color:#{};}}", scope, prefix, self.tags.guides)?;
+        self.tags.write_rules(io, &scope)
     }
 }
 
 /// Decorator for a particular record
 pub struct HtmlRecordDecorator {
-    level_color: &'static str,
-    style: StyleTable,
+    level: Level,
+    tags: Arc<StyleTags>,
+    /// Prefix of the classes elements are styled by, instead of `style` attributes
+    class_prefix: Option<Arc<String>>,
+    linkifier: Option<Arc<Linkifier>>,
+    collapse_lines: Option<usize>,
+    max_message_len: Option<usize>,
+    max_value_len: Option<usize>,
+    truncation: Truncation,
 }
 
-fn fmt(io: &mut io::Write,
-       f: &Fn(&mut io::Write) -> io::Result<()>,
-       style: &Style)
-       -> io::Result<()> {
-    if style.color.is_some() || style.bold || style.italic || style.custom.is_some() {
-        try!(write!(io, "<span style=\""));
-        if let Some(color) = style.color {
-            try!(write!(io, "color:#{};", color));
+/// Opening and closing tags of a style, rendered once when the decorator is built
+pub struct Tags {
+    open: String,
+    close: &'static str,
+}
+
+impl Tags {
+    /// Render the tags of `style`, using `default_color` if it has no color of its own
+    pub fn new(style: &Style, default_color: Option<&str>) -> Self {
+        Self::render(&declarations(style, style.color.or(default_color)), None, None, false)
+    }
+
+    /// Render tags applying `css` inline, or else as `class` of the stylesheet
+    ///
+    /// `isolated` text has its direction from its first strong character, and does not
+    /// affect the direction of the text around it.
+    fn render(css: &str, field: Option<&str>, class: Option<&str>, isolated: bool) -> Self {
+        if css.is_empty() && field.is_none() && class.is_none() && !isolated {
+            return Tags {
+                open: String::new(),
+                close: "",
+            };
         }
-        if style.bold {
-            try!(write!(io, "font-weight:bold;"));
+
+        let mut open = String::from("<span");
+        if let Some(field) = field {
+            open.push_str(" data-field=\"");
+            open.push_str(field);
+            open.push('"');
+        }
+        if isolated {
+            open.push_str(" dir=\"auto\"");
         }
-        if style.italic {
-            try!(write!(io, "font-style:italic;"));
+        match class {
+            Some(class) => {
+                open.push_str(" class=\"");
+                open.push_str(class);
+                open.push('"');
+            }
+            None if !css.is_empty() => {
+                open.push_str(" style=\"");
+                open.push_str(&escape(css));
+                open.push('"');
+            }
+            None => {}
         }
-        if let Some(custom) = style.custom {
-            try!(write!(io, "{}", custom));
+        open.push('>');
+        Tags {
+            open,
+            close: "</span>",
         }
-        try!(write!(io, "\">"));
-        try!(f(io));
-        try!(write!(io, "</span>"));
-    } else {
-        try!(f(io));
     }
-    Ok(())
+
+    pub fn fmt(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        self.fmt_html(io, &|io| f(&mut Escape(io)))
+    }
+
+    /// Like `fmt`, with `label` read by assistive tech instead of the text
+    ///
+    /// The element gets the `img` role, since generic elements cannot be labeled.
+    pub fn fmt_labeled(&self,
+                       io: &mut dyn io::Write,
+                       label: &str,
+                       f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                       -> io::Result<()> {
+        let (open, close) = match self.close {
+            "" => ("<span>", "</span>"),
+            close => (self.open.as_str(), close),
+        };
+        io.write_all(&open.as_bytes()[..open.len() - 1])?;
+        io.write_all(b" role=\"img\" aria-label=\"")?;
+        Escape(&mut *io).write_all(label.as_bytes())?;
+        io.write_all(b"\">")?;
+        f(&mut Escape(&mut *io))?;
+        io.write_all(close.as_bytes())
+    }
+
+    /// Like `fmt`, but `f` writes html instead of text
+    pub fn fmt_html(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        io.write_all(self.open.as_bytes())?;
+        f(io)?;
+        io.write_all(self.close.as_bytes())
+    }
 }
 
-impl RecordDecorator for HtmlRecordDecorator {
-    fn fmt_level(&self,
-                 io: &mut io::Write,
-                 f: &Fn(&mut io::Write) -> io::Result<()>)
-                 -> io::Result<()> {
-        try!(write!(io, "<span style=\""));
-        if let Some(color) = self.style.level.color {
-            try!(write!(io, "color:#{};", color));
+/// CSS declarations of `style`, in `color` instead of its own
+fn declarations(style: &Style, color: Option<&str>) -> String {
+    let mut css = String::new();
+    // `FormatBuilder::build` rejects invalid colors and custom CSS, other users get them
+    // left out
+    if let Some(color) = color.filter(|color| css::check_color(color).is_ok()) {
+        css.push_str("color:#");
+        css.push_str(color);
+        css.push(';');
+    }
+    if style.bold {
+        css.push_str("font-weight:bold;");
+    }
+    if style.italic {
+        css.push_str("font-style:italic;");
+    }
+    if let Some(Ok(custom)) = style.custom.map(css::sanitize) {
+        css.push_str(&custom);
+    }
+    css
+}
+
+/// Tags of every slot of a `StyleTable`
+pub struct StyleTags {
+    /// Tags of each level, indexed by `Level::as_usize() - 1`
+    levels: Vec<Tags>,
+    pub timestamp: Tags,
+    pub message: Tags,
+    pub key: Tags,
+    pub value: Tags,
+    pub separator: Tags,
+    pub duration: Tags,
+    pub gap: Tags,
+    /// Colors of context lanes, used in turn
+    pub lanes: Vec<String>,
+    /// Color of tree guide lines
+    pub guides: String,
+    /// Class of each slot with its CSS declarations, for the stylesheet
+    rules: Vec<(String, String)>,
+}
+
+impl StyleTags {
+    /// Tags with inline styles
+    pub fn new(color_palette: &ColorPalette, style: &StyleTable) -> Self {
+        Self::render(color_palette, style, None)
+    }
+
+    /// Tags with the classes of `write_rules`, starting with `prefix`, instead of inline styles
+    pub fn with_classes(color_palette: &ColorPalette, style: &StyleTable, prefix: &str) -> Self {
+        Self::render(color_palette, style, Some(prefix))
+    }
+
+    fn render(color_palette: &ColorPalette, style: &StyleTable, prefix: Option<&str>) -> Self {
+        let adjust = |color: &str| match style.adjust_contrast {
+            Some(background) => contrast::adjust(color, background),
+            None => color.to_owned(),
+        };
+        let mut rules = Vec::new();
+        let mut tags = |slot: &Style, default_color: Option<&str>, field, class: String| {
+            // Messages, keys and values may be written right to left, or mix directions
+            let isolated = field == Some(FIELD_MESSAGE) || field == Some(FIELD_KEY) ||
+                           field == Some(FIELD_VALUE);
+            let color = slot.color.or(default_color);
+            let adjusted = match (color, style.adjust_contrast) {
+                (Some(color), Some(background)) => Some(contrast::adjust(color, background)),
+                _ => None,
+            };
+            let css = declarations(slot, adjusted.as_deref().or(color));
+            let class = format!("{}{}", prefix.unwrap_or(""), class);
+            let tags = Tags::render(&css, field, prefix.map(|_| &*class), isolated);
+            rules.push((class, css));
+            tags
+        };
+        let levels = (1..)
+            .map_while(Level::from_usize)
+            .map(|level| {
+                tags(&style.level,
+                     Some(color_palette.level_to_color(level)),
+                     Some(FIELD_LEVEL),
+                     format!("level-{}", level.as_str().to_ascii_lowercase()))
+            })
+            .collect();
+        StyleTags {
+            levels,
+            timestamp: tags(&style.timestamp, None, Some(FIELD_TIMESTAMP), "ts".to_owned()),
+            message: tags(&style.message, None, Some(FIELD_MESSAGE), "msg".to_owned()),
+            key: tags(&style.key, None, Some(FIELD_KEY), "key".to_owned()),
+            value: tags(&style.value, None, Some(FIELD_VALUE), "value".to_owned()),
+            separator: tags(&style.separator, None, None, "separator".to_owned()),
+            duration: tags(&style.duration, None, Some(FIELD_DURATION), "duration".to_owned()),
+            gap: tags(&style.gap, None, Some(FIELD_DURATION), "gap".to_owned()),
+            lanes: LANE_COLORS.iter().map(|color| adjust(color)).collect(),
+            guides: adjust(GUIDE_COLOR),
+            rules,
+        }
+    }
+
+    pub fn level(&self, level: Level) -> &Tags {
+        &self.levels[level.as_usize() - 1]
+    }
+
+    /// Write a CSS rule for the class of every slot with a style, after the `scope` selector
+    pub fn write_rules(&self, io: &mut dyn io::Write, scope: &str) -> io::Result<()> {
+        for (class, css) in &self.rules {
+            if !css.is_empty() {
+                writeln!(io, "{}.{}{{{}}}", scope, class, css)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Buffer messages and values are formatted into before they are rendered
+    static TEXT_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Start of the document prologue, up to where a stylesheet may be added to its head
+pub const DOCUMENT_HEAD_START: &str = "<!DOCTYPE html>\n<html>\n<head>\n<meta \
+                                                charset=\"utf-8\">\n";
+
+/// Rest of the document prologue, opening the container of the records for assistive tech
+pub const DOCUMENT_HEAD_END: &str = "</head>\n<body>\n<div role=\"log\">\n";
+
+const DOCUMENT_EPILOGUE: &str = "</div>\n</body>\n</html>\n";
+
+/// `data-field` of levels
+pub const FIELD_LEVEL: &str = "level";
+
+/// `data-field` of timestamps
+pub const FIELD_TIMESTAMP: &str = "ts";
+
+/// `data-field` of messages
+pub const FIELD_MESSAGE: &str = "msg";
+
+/// `data-field` of keys
+pub const FIELD_KEY: &str = "key";
+
+/// `data-field` of values
+pub const FIELD_VALUE: &str = "value";
+
+/// `data-field` of elapsed and delta times
+pub const FIELD_DURATION: &str = "duration";
+
+/// Opening tag of a line
+pub const LINE_START: &str = "<pre style=\"margin-bottom:-0.5em\">";
+
+/// Closing tag of a line
+pub const LINE_END: &str = "</pre>\n";
+
+/// Prefix of the classes of the stylesheet of a document
+pub const CLASS_PREFIX: &str = "slog-";
+
+/// Style of lines, narrowing the default margins of `<pre>` elements between them
+const LINE_STYLE: &str = "margin-bottom:-0.5em";
+
+/// Rule for lines in the stylesheet, spacing them like `LINE_STYLE` without a negative margin
+const LINE_RULE: &str = "margin:0.5em 0";
+
+/// Style of multi-line blocks and collapsible elements, kept inline with the surrounding text
+const BLOCK_STYLE: &str = "display:inline-block;vertical-align:top";
+
+/// Style of output that did not change since it was printed last
+const UNCHANGED_STYLE: &str = "opacity:0.5;";
+
+/// Classes of the stylesheet for the fixed styles
+const FIXED_STYLES: [(&str, &str); 3] = [("line", LINE_RULE),
+                                         ("block", BLOCK_STYLE),
+                                         ("unchanged", UNCHANGED_STYLE)];
+
+/// Colors of context lanes, used in turn
+pub const LANE_COLORS: [&str; 8] = ["1f77b4", "b35900", "248624", "d62728", "8e62b5",
+                                    "8c564b", "aa5991", "0f818c"];
+
+/// Color of tree guide lines
+pub const GUIDE_COLOR: &str = "767676";
+
+impl HtmlRecordDecorator {
+    /// Write the opening tag of `element` with a fixed style, inline or as `class`
+    fn open(&self,
+            io: &mut dyn io::Write,
+            element: &str,
+            class: &str,
+            css: &str)
+            -> io::Result<()> {
+        write!(io, "<{}", element)?;
+        self.write_style(io, class, css)?;
+        write!(io, ">")
+    }
+
+    /// Write the attribute applying a fixed style, inline or as `class`
+    fn write_style(&self, io: &mut dyn io::Write, class: &str, css: &str) -> io::Result<()> {
+        if let Some(ref prefix) = self.class_prefix {
+            write!(io, " class=\"{}{}\"", prefix, class)
         } else {
-            try!(write!(io, "color:#{};", self.level_color));
+            write!(io, " style=\"{}\"", css)
+        }
+    }
+
+    /// Format a message or value
+    ///
+    /// Text longer than `max_len` bytes is truncated according to `truncation`.
+    fn fmt_text(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>,
+                tags: &Tags,
+                max_len: Option<usize>)
+                -> io::Result<()> {
+        with_buffer(&TEXT_BUFFER, |buf| {
+            f(buf)?;
+            let text = String::from_utf8_lossy(buf);
+            self.fmt_truncated(io, text.trim_end_matches(&['\n', '\r'][..]), tags, max_len)
+        })
+    }
+
+    /// Format text, truncated after `max_len` bytes
+    fn fmt_truncated(&self,
+                     io: &mut dyn io::Write,
+                     text: &str,
+                     tags: &Tags,
+                     max_len: Option<usize>)
+                     -> io::Result<()> {
+        let mut end = match max_len {
+            Some(max_len) if text.len() > max_len => max_len,
+            _ => return self.fmt_lines(io, text, tags),
+        };
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let (text, overflow) = text.split_at(end);
+        self.fmt_lines(io, text, tags)?;
+        let marker = |io: &mut dyn io::Write| io.write_all(bytes_marker(overflow.len()).as_bytes());
+        match self.truncation {
+            Truncation::Expand => {
+                self.open(io, "details", "block", BLOCK_STYLE)?;
+                write!(io, "<summary>")?;
+                self.tags.separator.fmt(io, &marker)?;
+                write!(io, "</summary>")?;
+                self.fmt_lines(io, overflow, tags)?;
+                write!(io, "</details>")
+            }
+            Truncation::Drop => self.tags.separator.fmt(io, &marker),
+        }
+    }
+
+    /// Columns text takes up as formatted by `fmt_text`, truncated after `max_len` bytes
+    fn formatted_width(&self, text: &str, max_len: Option<usize>) -> usize {
+        let text = text.trim_end_matches(&['\n', '\r'][..]);
+        let mut end = text.len();
+        if let Some(max_len) = max_len {
+            end = cmp::min(end, max_len);
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
         }
-        if self.style.level.bold {
-            try!(write!(io, "font-weight:bold;"));
+        let (text, overflow) = text.split_at(end);
+        let lines = text.lines().count();
+        let visible = match self.collapse_lines {
+            Some(max) if lines > max => cmp::max(max, 1),
+            _ => lines,
+        };
+        let mut columns = text.lines().take(visible).map(width::text_width).max().unwrap_or(0);
+        if visible < lines {
+            columns = cmp::max(columns, width::text_width(&lines_marker(lines - visible)));
         }
-        if self.style.level.italic {
-            try!(write!(io, "font-style:italic;"));
+        if !overflow.is_empty() {
+            columns += width::text_width(&bytes_marker(overflow.len()));
         }
-        if let Some(custom) = self.style.level.custom {
-            try!(write!(io, "{}", custom));
+        columns
+    }
+
+    /// Format text that may span several lines
+    ///
+    /// Multiple lines are kept in a block aligned with the first one, and collapsed after
+    /// `collapse_lines` lines.
+    fn fmt_lines(&self, io: &mut dyn io::Write, text: &str, tags: &Tags) -> io::Result<()> {
+        if !text.contains('\n') {
+            return self.fmt_linked(io, text, tags);
+        }
+
+        let lines = text.lines().count();
+        let visible = match self.collapse_lines {
+            Some(max) if lines > max => cmp::max(max, 1),
+            _ => lines,
+        };
+        self.open(io, "span", "block", BLOCK_STYLE)?;
+        if visible < lines {
+            let (split, _) = text.match_indices('\n').nth(visible - 1).expect("line is hidden");
+            let marker = lines_marker(lines - visible);
+            self.fmt_linked_lines(io, &text[..split], tags)?;
+            write!(io, "<details data-break><summary>")?;
+            self.tags.separator.fmt(io, &|io| io.write_all(marker.as_bytes()))?;
+            write!(io, "</summary>")?;
+            self.fmt_linked_lines(io, &text[split + 1..], tags)?;
+            write!(io, "</details>")?;
+        } else {
+            self.fmt_linked_lines(io, text, tags)?;
+        }
+        write!(io, "</span>")
+    }
+
+    /// Format text that may contain links
+    fn fmt_linked(&self, io: &mut dyn io::Write, text: &str, tags: &Tags) -> io::Result<()> {
+        tags.fmt_html(io, &|io| self.write_linked(io, text))
+    }
+
+    /// Like `fmt_linked`, but with line endings normalized to `\n`
+    fn fmt_linked_lines(&self, io: &mut dyn io::Write, text: &str, tags: &Tags) -> io::Result<()> {
+        tags.fmt_html(io, &|io| {
+            for (i, line) in text.lines().enumerate() {
+                if i > 0 {
+                    io.write_all(b"\n")?;
+                }
+                self.write_linked(io, line)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Write text as html, with links if a linkifier is set
+    fn write_linked(&self, io: &mut dyn io::Write, text: &str) -> io::Result<()> {
+        match self.linkifier {
+            Some(ref linkifier) => linkifier.write_html(io, text),
+            None => Escape(io).write_all(text.as_bytes()),
+        }
+    }
+
+    /// Format the members of an array or object, one per line
+    fn fmt_tree_children(&self, io: &mut dyn io::Write, node: &TreeNode) -> io::Result<()> {
+        match *node {
+            TreeNode::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.fmt_tree_line(io, i, None, item)?;
+                }
+            }
+            TreeNode::Object(ref members) => {
+                for (i, (key, value)) in members.iter().enumerate() {
+                    self.fmt_tree_line(io, i, Some(key), value)?;
+                }
+            }
+            _ => {}
         }
-        try!(write!(io, "\">"));
-        try!(f(io));
-        try!(write!(io, "</span>"));
         Ok(())
     }
 
+    /// Format a single member of an array or object
+    ///
+    /// `<summary>` is a block, so the first line is already started.
+    fn fmt_tree_line(&self,
+                     io: &mut dyn io::Write,
+                     index: usize,
+                     key: Option<&str>,
+                     node: &TreeNode)
+                     -> io::Result<()> {
+        if index > 0 {
+            writeln!(io)?;
+        }
+        write!(io, "  ")?;
+        if let Some(key) = key {
+            self.tags.key.fmt(io, &|io| io.write_all(key.as_bytes()))?;
+            self.tags.separator.fmt(io, &|io| io.write_all(b": "))?;
+        }
+        if node.is_container() {
+            self.open(io, "details", "block", BLOCK_STYLE)?;
+            write!(io, "<summary>")?;
+            self.tags.separator.fmt(io, &|io| write!(io, "{}", node.outline()))?;
+            write!(io, "</summary>")?;
+            self.fmt_tree_children(io, node)?;
+            write!(io, "</details>")
+        } else {
+            self.fmt_linked(io, &node.to_string(), &self.tags.value)
+        }
+    }
+}
+
+impl MarkupDecorator for HtmlRecordDecorator {
+    /// Width of the first line of the message, or of its block of lines, as truncated and
+    /// collapsed
+    fn msg_width(&self, msg: &str) -> usize {
+        self.formatted_width(msg, self.max_message_len)
+    }
+
+    fn value_width(&self, value: &str) -> usize {
+        self.formatted_width(value, self.max_value_len)
+    }
+
+    fn fmt_structured_value(&self,
+                            io: &mut dyn io::Write,
+                            value: &StructuredValue)
+                            -> io::Result<()> {
+        let summary = value.summary();
+        if self.max_value_len.is_some_and(|max_len| summary.len() > max_len) {
+            return self.fmt_value(io, &|io| write!(io, "{}", summary));
+        }
+        let raw = match *value {
+            StructuredValue::Tree(_) => &summary,
+            StructuredValue::Block(ref text) => text,
+        };
+        write!(io, "<details data-field=\"{}\" data-raw=\"", FIELD_VALUE)?;
+        Escape(&mut *io).write_all(raw.as_bytes())?;
+        write!(io, "\"")?;
+        self.write_style(io, "block", BLOCK_STYLE)?;
+        write!(io, "><summary>")?;
+        self.tags.value.fmt(io, &|io| io.write_all(summary.as_bytes()))?;
+        write!(io, "</summary>")?;
+        match *value {
+            StructuredValue::Tree(ref node) => self.fmt_tree_children(io, node)?,
+            StructuredValue::Block(ref text) => {
+                self.fmt_linked(io, text, &self.tags.value)?
+            }
+        }
+        write!(io, "</details>")
+    }
+
+    fn fmt_duration(&self,
+                    io: &mut dyn io::Write,
+                    f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>,
+                    gap: bool)
+                    -> io::Result<()> {
+        let tags = if gap {
+            &self.tags.gap
+        } else {
+            &self.tags.duration
+        };
+        tags.fmt(io, f)
+    }
+
+    fn fmt_unchanged(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        self.open(io, "span", "unchanged", UNCHANGED_STYLE)?;
+        f(io)?;
+        write!(io, "</span>")
+    }
+
+    fn fmt_lane(&self,
+                io: &mut dyn io::Write,
+                lane: usize,
+                parent: Option<usize>)
+                -> io::Result<()> {
+        let color = &self.tags.lanes[lane % LANE_COLORS.len()];
+        write!(io, "<span data-lane=\"{}\"", lane)?;
+        if let Some(parent) = parent {
+            write!(io, " data-parent=\"{}\"", parent)?;
+        }
+        let class = format!("lane-{}", lane % LANE_COLORS.len());
+        self.write_style(io, &class, &format!("color:#{};", color))?;
+        write!(io, ">\u{258c}</span> ")
+    }
+
+    fn fmt_indent(&self, io: &mut dyn io::Write, indent: usize) -> io::Result<()> {
+        if indent == 0 {
+            return Ok(());
+        }
+        write!(io, "<span data-depth=\"{}\">", indent)?;
+        for _ in 0..indent {
+            io.write_all(b"  ")?;
+        }
+        write!(io, "</span>")
+    }
+
+    fn fmt_guides(&self, io: &mut dyn io::Write, depth: usize) -> io::Result<()> {
+        if depth == 0 {
+            return Ok(());
+        }
+        write!(io, "<span data-depth=\"{}\"", depth)?;
+        self.write_style(io, "guides", &format!("color:#{};", self.tags.guides))?;
+        write!(io, ">")?;
+        write_guides(io, depth)?;
+        write!(io, "</span>")
+    }
+
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        self.open(io, "pre", "line", LINE_STYLE)?;
+        f(io)?;
+        io.write_all(LINE_END.as_bytes())
+    }
+}
+
+/// Marker of the bytes cut off truncated text
+fn bytes_marker(bytes: usize) -> String {
+    format!("\u{2026} (+{} bytes)", bytes)
+}
+
+/// Marker of the lines hidden in collapsed text
+fn lines_marker(hidden: usize) -> String {
+    let unit = if hidden == 1 { "line" } else { "lines" };
+    format!("\u{2026} (+{} {})", hidden, unit)
+}
+
+/// Full name of a level, read out by screen readers
+fn level_label(level: Level) -> &'static str {
+    match level {
+        Level::Critical => "critical",
+        Level::Error => "error",
+        Level::Warning => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+impl RecordDecorator for HtmlRecordDecorator {
+    /// Format the level, told by its color and abbreviated name
+    ///
+    /// Screen readers read the full name of the level from its label instead. Unlike hidden
+    /// text, the label is not copied along with the line.
+    fn fmt_level(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        self.tags.level(self.level).fmt_labeled(io, level_label(self.level), f)
+    }
+
     fn fmt_msg(&self,
-               io: &mut io::Write,
-               f: &Fn(&mut io::Write) -> io::Result<()>)
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
                -> io::Result<()> {
-        fmt(io, f, &self.style.message)
+        self.fmt_text(io, f, &self.tags.message, self.max_message_len)
     }
 
     fn fmt_key(&self,
-               io: &mut io::Write,
-               f: &Fn(&mut io::Write) -> io::Result<()>)
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
                -> io::Result<()> {
-        fmt(io, f, &self.style.key)
+        self.tags.key.fmt(io, f)
     }
 
     fn fmt_separator(&self,
-               io: &mut io::Write,
-               f: &Fn(&mut io::Write) -> io::Result<()>)
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
                -> io::Result<()> {
-        fmt(io, f, &self.style.separator)
+        self.tags.separator.fmt(io, f)
     }
 
     fn fmt_value(&self,
-               io: &mut io::Write,
-               f: &Fn(&mut io::Write) -> io::Result<()>)
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
                -> io::Result<()> {
-        fmt(io, f, &self.style.value)
+        self.fmt_text(io, f, &self.tags.value, self.max_value_len)
     }
 
     fn fmt_timestamp(&self,
-               io: &mut io::Write,
-               f: &Fn(&mut io::Write) -> io::Result<()>)
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
                -> io::Result<()> {
-        fmt(io, f, &self.style.timestamp)
+        self.tags.timestamp.fmt(io, f)
     }
 }
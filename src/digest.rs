@@ -0,0 +1,413 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use slog::{Drain, Level, OwnedKeyValueList, Record};
+use slog_stream::{Decorator, RecordDecorator};
+
+use Format;
+use decorator::{DocumentDecorator, MarkupDecorator};
+use serializer::Serializer;
+
+/// Drain collecting records at or above a level, rendered later as a single document
+///
+/// Records are formatted as they are logged, and kept for the configured window. Rendering
+/// writes the document prologue, a heading summarizing the records, the records and the
+/// epilogue. With the format of `FormatBuilder::build_email` the result is a
+/// self-contained email body.
+///
+/// Records can also be grouped by a key like `request_id`, so that interleaved requests
+/// read top to bottom. Each group gets a section with a heading of its own.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate slog;
+/// # extern crate slog_html;
+/// #
+/// # use slog::DrainExt;
+/// #
+/// # use std::time::Duration;
+/// #
+/// # fn main() {
+/// let digest = slog_html::digest(slog_html::new().build_email().unwrap())
+///     .level(slog::Level::Error)
+///     .window(Duration::from_secs(24 * 60 * 60))
+///     .title("Nightly errors")
+///     .build();
+///
+/// let log = slog::Logger::root(digest.clone().fuse(), o!());
+/// info!(log, "backup started");
+/// error!(log, "backup failed"; "volume" => "/srv");
+///
+/// let mut body = Vec::new();
+/// digest.render(&mut body).unwrap();
+/// digest.clear();
+///
+/// let body = String::from_utf8(body).unwrap();
+/// assert!(body.contains("backup failed"));
+/// assert!(!body.contains("backup started"));
+/// # }
+/// ```
+pub struct Digest<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    inner: Arc<Inner<D>>,
+}
+
+struct Inner<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    format: Format<D>,
+    level: Level,
+    window: Option<Duration>,
+    max_records: usize,
+    title: String,
+    group_by: Vec<&'static str>,
+    entries: Mutex<Entries>,
+}
+
+/// Records of a digest, oldest first
+#[derive(Clone)]
+struct Entries {
+    records: VecDeque<Arc<Entry>>,
+    /// Number of records left out to stay within `max_records`
+    omitted: usize,
+}
+
+/// Formatted record
+struct Entry {
+    level: Level,
+    logged: Instant,
+    timestamp: String,
+    /// Grouping key and its value
+    group: Option<(&'static str, String)>,
+    output: Vec<u8>,
+}
+
+/// Records of a group, in the order they were logged
+type Group<'a> = (Option<&'a (&'static str, String)>, Vec<&'a Entry>);
+
+/// Decorator writing values only, to extract the value of a grouping key
+struct ValueDecorator;
+
+impl RecordDecorator for ValueDecorator {
+    fn fmt_key(&self,
+               _: &mut dyn io::Write,
+               _: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MarkupDecorator for ValueDecorator {
+    fn fmt_key_separator(&self, _: &mut dyn io::Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `Digest` builder
+pub struct DigestBuilder<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    format: Format<D>,
+    level: Level,
+    window: Option<Duration>,
+    max_records: usize,
+    title: String,
+    group_by: Vec<&'static str>,
+}
+
+impl<D> DigestBuilder<D>
+    where D: DocumentDecorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    /// New `DigestBuilder` collecting records formatted by `format`
+    pub fn new(format: Format<D>) -> Self {
+        DigestBuilder {
+            format,
+            level: Level::Error,
+            window: None,
+            max_records: 1000,
+            title: "Log digest".to_owned(),
+            group_by: Vec::new(),
+        }
+    }
+
+    /// Collect records at or above `level` (default: `Level::Error`)
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Keep records for `window` only
+    ///
+    /// By default, records are kept until the digest is cleared.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Keep at most `max_records` records, dropping the oldest ones (default: 1000)
+    ///
+    /// The number of dropped records is part of the summary. In compact mode, dropped
+    /// records may take context headers with them, so prefer full mode with a limit.
+    pub fn max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Title of the summary heading (default: "Log digest")
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Group records by the value of `key`, in a section per value
+    ///
+    /// Sections are ordered by their first record, and records without the key come last.
+    /// If called more than once, records are grouped by the first of the keys they have.
+    /// Use full mode to group records, since context headers of compact mode are only
+    /// written when the context changes.
+    pub fn group_by(mut self, key: &'static str) -> Self {
+        self.group_by.push(key);
+        self
+    }
+
+    /// Build `Digest`
+    pub fn build(self) -> Digest<D> {
+        Digest {
+            inner: Arc::new(Inner {
+                format: self.format,
+                level: self.level,
+                window: self.window,
+                max_records: self.max_records,
+                title: self.title,
+                group_by: self.group_by,
+                entries: Mutex::new(Entries {
+                    records: VecDeque::new(),
+                    omitted: 0,
+                }),
+            }),
+        }
+    }
+}
+
+impl<D> Digest<D>
+    where D: DocumentDecorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    /// Write the collected records as a document, with a summary heading
+    ///
+    /// The records are written after releasing the lock on them, so a slow writer does not
+    /// block logging.
+    pub fn render(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        let inner = &self.inner;
+        let entries = {
+            let mut entries = inner.lock();
+            inner.prune(&mut entries, Instant::now());
+            entries.clone()
+        };
+
+        inner.format.write_prologue(io)?;
+        inner.format.decorator.write_heading(io, &|io| inner.write_summary(io, &entries))?;
+        if inner.group_by.is_empty() {
+            for entry in &entries.records {
+                io.write_all(&entry.output)?;
+            }
+        } else {
+            for (group, records) in groups(&entries.records) {
+                inner.format.decorator.write_heading(io, &|io| {
+                    match group {
+                        Some(&(key, ref value)) => write!(io, "{} {}: ", key, value)?,
+                        None => write!(io, "Ungrouped: ")?,
+                    }
+                    write_group_summary(io, &records)
+                })?;
+                for entry in records {
+                    io.write_all(&entry.output)?;
+                }
+            }
+        }
+        inner.format.write_epilogue(io)
+    }
+
+    /// Number of collected records
+    pub fn len(&self) -> usize {
+        let inner = &self.inner;
+        let mut entries = inner.lock();
+        inner.prune(&mut entries, Instant::now());
+        entries.records.len()
+    }
+
+    /// Whether no records were collected
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all collected records, e.g. once they are sent
+    pub fn clear(&self) {
+        let mut entries = self.inner.lock();
+        entries.records.clear();
+        entries.omitted = 0;
+    }
+}
+
+/// Split records into groups, ordered by their first record, with ungrouped records last
+fn groups(records: &VecDeque<Arc<Entry>>) -> Vec<Group<'_>> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index = HashMap::new();
+    let mut ungrouped: Vec<&Entry> = Vec::new();
+    for entry in records {
+        match entry.group {
+            Some(ref group) => {
+                let i = *index.entry(group).or_insert(groups.len());
+                if i == groups.len() {
+                    groups.push((Some(group), Vec::new()));
+                }
+                groups[i].1.push(entry);
+            }
+            None => ungrouped.push(entry),
+        }
+    }
+    if !ungrouped.is_empty() {
+        groups.push((None, ungrouped));
+    }
+    groups
+}
+
+/// Write the number of records of a group, the worst level and their time range
+fn write_group_summary(io: &mut dyn io::Write, records: &[&Entry]) -> io::Result<()> {
+    let unit = if records.len() == 1 { "record" } else { "records" };
+    write!(io, "{} {}", records.len(), unit)?;
+    if let Some(worst) = records.iter().map(|entry| entry.level).min_by_key(Level::as_usize) {
+        write!(io, ", worst {}", worst.as_str())?;
+    }
+    if let (Some(first), Some(last)) = (records.first(), records.last()) {
+        write!(io, " ({} \u{2013} {})", first.timestamp, last.timestamp)?;
+    }
+    Ok(())
+}
+
+impl<D> Inner<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().expect("failed to lock digest records")
+    }
+
+    /// Drop records that fell out of the window
+    fn prune(&self, entries: &mut Entries, now: Instant) {
+        if let Some(window) = self.window {
+            while entries.records.front().is_some_and(|entry| now - entry.logged > window) {
+                entries.records.pop_front();
+            }
+        }
+    }
+
+    /// Write the title, the number of records of each level and their time range
+    fn write_summary(&self, io: &mut dyn io::Write, entries: &Entries) -> io::Result<()> {
+        write!(io, "{}: ", self.title)?;
+        if entries.records.is_empty() {
+            write!(io, "no records")?;
+        }
+        let mut first = true;
+        for level in (1..).map_while(Level::from_usize) {
+            let count = entries.records.iter().filter(|entry| entry.level == level).count();
+            if count > 0 {
+                write!(io, "{}{} {}", if first { "" } else { ", " }, count, level.as_str())?;
+                first = false;
+            }
+        }
+        if entries.omitted > 0 {
+            write!(io, ", {} more omitted", entries.omitted)?;
+        }
+        if let (Some(first), Some(last)) = (entries.records.front(), entries.records.back()) {
+            write!(io, " ({} \u{2013} {})", first.timestamp, last.timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Find the first grouping key of a record, preferring its own values to its logger's
+    fn group(&self,
+             record: &Record,
+             logger_values: &OwnedKeyValueList)
+             -> io::Result<Option<(&'static str, String)>> {
+        for &key in &self.group_by {
+            let mut serializer = Serializer::new(Vec::new(), &ValueDecorator, false);
+            let mut found = false;
+            for &(k, v) in record.values().iter() {
+                if k == key {
+                    v.serialize(record, k, &mut serializer)?;
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                for (k, v) in logger_values.iter() {
+                    if k == key {
+                        v.serialize(record, k, &mut serializer)?;
+                        found = true;
+                        break;
+                    }
+                }
+            }
+            if found {
+                let value = serializer.finish();
+                return Ok(Some((key, String::from_utf8_lossy(&value).into_owned())));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<D> Clone for Digest<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    fn clone(&self) -> Self {
+        Digest { inner: self.inner.clone() }
+    }
+}
+
+impl<D> Drain for Digest<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    type Error = io::Error;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKeyValueList) -> io::Result<()> {
+        let inner = &self.inner;
+        if !record.level().is_at_least(inner.level) {
+            return Ok(());
+        }
+
+        // Formatting under the lock keeps the records in the order of compact mode headers
+        let mut entries = inner.lock();
+        // The timestamp of the summary is the one of the formatted record
+        let mut timestamp = Vec::new();
+        (inner.format.fn_timestamp)(&mut timestamp)?;
+        let mut output = Vec::new();
+        inner.format
+            .format_with_timestamp(&mut output,
+                                   record,
+                                   logger_values,
+                                   &|io| io.write_all(&timestamp))?;
+        let group = inner.group(record, logger_values)?;
+        let now = Instant::now();
+        inner.prune(&mut entries, now);
+        entries.records.push_back(Arc::new(Entry {
+            level: record.level(),
+            logged: now,
+            timestamp: String::from_utf8_lossy(&timestamp).into_owned(),
+            group,
+            output,
+        }));
+        while entries.records.len() > inner.max_records {
+            entries.records.pop_front();
+            entries.omitted += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+
+use slog::{Level, Record};
+use slog_stream::{Decorator, RecordDecorator};
+
+use buffer::with_buffer;
+use color_palette::ColorPalette;
+use decorator::{DocumentDecorator, LANE_COLORS, MarkupDecorator, StyleTags, Tags, write_guides};
+use escape::Escape;
+use linkify::Linkifier;
+use style::StyleTable;
+
+/// Html decorator for email bodies
+///
+/// Mail clients drop `<style>` blocks and support few CSS properties, so the document has no
+/// `<style>` element and every style is inline. Records are rows of a layout table: the
+/// body, table and cells set `margin`, `padding`, `border-*`, `line-height` and
+/// `white-space`, text is styled with `color` and `font-*`. Line breaks are `<br>`
+/// elements, and nothing is collapsible: truncated text is dropped, structured values show
+/// their one-line summary.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate slog;
+/// # extern crate slog_html;
+/// # extern crate slog_stream;
+/// #
+/// # use slog::DrainExt;
+/// #
+/// # use std::io;
+/// #
+/// # fn main() {
+/// let format = slog_html::new().max_value_len(200).build_email().unwrap();
+///
+/// let log = slog::Logger::root(slog_stream::stream(io::stdout(), format).fuse(), o!());
+/// error!(log, "backup failed"; "volume" => "/srv", "error" => "disk full");
+/// # }
+/// ```
+pub struct EmailDecorator {
+    tags: Arc<StyleTags>,
+    linkifier: Option<Arc<Linkifier>>,
+    max_message_len: Option<usize>,
+    max_value_len: Option<usize>,
+}
+
+impl EmailDecorator {
+    /// New `EmailDecorator` coloring levels from `color_palette`
+    pub fn new(color_palette: ColorPalette, style: StyleTable) -> Self {
+        EmailDecorator {
+            tags: Arc::new(StyleTags::new(&color_palette, &style)),
+            linkifier: None,
+            max_message_len: None,
+            max_value_len: None,
+        }
+    }
+
+    /// Turn URLs and other patterns in messages and values into links
+    pub fn linkifier(mut self, linkifier: Option<Linkifier>) -> Self {
+        self.linkifier = linkifier.map(Arc::new);
+        self
+    }
+
+    /// Truncate messages and values longer than the given number of bytes
+    pub fn truncate(mut self, max_message_len: Option<usize>, max_value_len: Option<usize>) -> Self {
+        self.max_message_len = max_message_len;
+        self.max_value_len = max_value_len;
+        self
+    }
+}
+
+impl Decorator for EmailDecorator {
+    type RecordDecorator = EmailRecordDecorator;
+
+    fn decorate(&self, record: &Record) -> EmailRecordDecorator {
+        EmailRecordDecorator {
+            level: record.level(),
+            tags: self.tags.clone(),
+            linkifier: self.linkifier.clone(),
+            max_message_len: self.max_message_len,
+            max_value_len: self.max_value_len,
+        }
+    }
+}
+
+impl DocumentDecorator for EmailDecorator {
+    fn write_prologue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        io.write_all(DOCUMENT_PROLOGUE.as_bytes())
+    }
+
+    fn write_epilogue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        io.write_all(DOCUMENT_EPILOGUE.as_bytes())
+    }
+
+    fn write_heading(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        with_buffer(&TEXT_BUFFER, |buf| {
+            f(buf)?;
+            io.write_all(HEADING_START.as_bytes())?;
+            write_lines(io, &String::from_utf8_lossy(buf), None)?;
+            io.write_all(ROW_END.as_bytes())
+        })
+    }
+}
+
+/// Decorator for a particular record
+pub struct EmailRecordDecorator {
+    level: Level,
+    tags: Arc<StyleTags>,
+    linkifier: Option<Arc<Linkifier>>,
+    max_message_len: Option<usize>,
+    max_value_len: Option<usize>,
+}
+
+thread_local! {
+    /// Buffer messages and values are formatted into before they are rendered
+    static TEXT_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+const DOCUMENT_PROLOGUE: &str = "<!DOCTYPE html>\n<html>\n<head>\n<meta \
+                                          charset=\"utf-8\">\n</head>\n<body \
+                                          style=\"margin:0;padding:0;\">\n<table \
+                                          role=\"presentation\" width=\"100%\" \
+                                          cellpadding=\"0\" cellspacing=\"0\" border=\"0\" \
+                                          style=\"border-collapse:collapse;\">\n";
+
+const DOCUMENT_EPILOGUE: &str = "</table>\n</body>\n</html>\n";
+
+/// Opening tags of a row holding a line
+const ROW_START: &str = "<tr><td style=\"font-family:Menlo,Consolas,'Courier \
+                                 New',monospace;font-size:13px;line-height:18px;\
+                                 white-space:pre-wrap;padding:1px 4px;\">";
+
+/// Opening tags of a row holding a heading
+const HEADING_START: &str = "<tr><td style=\"font-family:Arial,Helvetica,sans-serif;\
+                                     font-size:16px;font-weight:bold;padding:8px \
+                                     4px;border-bottom:1px solid #cccccc;\">";
+
+/// Closing tags of a row
+const ROW_END: &str = "</td></tr>\n";
+
+/// Color of output that did not change since it was printed last
+const UNCHANGED_COLOR: &str = "999999";
+
+/// Write text as html, with line breaks as `<br>` elements
+fn write_lines(io: &mut dyn io::Write,
+               text: &str,
+               linkifier: Option<&Linkifier>)
+               -> io::Result<()> {
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            io.write_all(b"<br>")?;
+        }
+        (match linkifier {
+            Some(linkifier) => linkifier.write_html(io, line),
+            None => Escape(&mut *io).write_all(line.as_bytes()),
+        })?;
+    }
+    Ok(())
+}
+
+impl EmailRecordDecorator {
+    /// Format a message or value, dropping everything after `max_len` bytes
+    fn fmt_text(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>,
+                tags: &Tags,
+                max_len: Option<usize>)
+                -> io::Result<()> {
+        with_buffer(&TEXT_BUFFER, |buf| {
+            f(buf)?;
+            let text = String::from_utf8_lossy(buf);
+            let text = text.trim_end_matches(&['\n', '\r'][..]);
+            let mut end = match max_len {
+                Some(max_len) if text.len() > max_len => max_len,
+                _ => text.len(),
+            };
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            let linkifier = self.linkifier.as_deref();
+            tags.fmt_html(io, &|io| write_lines(io, &text[..end], linkifier))?;
+            if end < text.len() {
+                let overflow = text.len() - end;
+                (self.tags
+                    .separator
+                    .fmt(io, &|io| write!(io, "\u{2026} (+{} bytes)", overflow)))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl MarkupDecorator for EmailRecordDecorator {
+    fn fmt_duration(&self,
+                    io: &mut dyn io::Write,
+                    f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>,
+                    gap: bool)
+                    -> io::Result<()> {
+        let tags = if gap {
+            &self.tags.gap
+        } else {
+            &self.tags.duration
+        };
+        tags.fmt(io, f)
+    }
+
+    fn fmt_unchanged(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        write!(io, "<span style=\"color:#{};\">", UNCHANGED_COLOR)?;
+        f(io)?;
+        write!(io, "</span>")
+    }
+
+    fn fmt_lane(&self, io: &mut dyn io::Write, lane: usize, _: Option<usize>) -> io::Result<()> {
+        let color = &self.tags.lanes[lane % LANE_COLORS.len()];
+        write!(io, "<span style=\"color:#{};\">\u{258c}</span> ", color)
+    }
+
+    fn fmt_guides(&self, io: &mut dyn io::Write, depth: usize) -> io::Result<()> {
+        if depth == 0 {
+            return Ok(());
+        }
+        write!(io, "<span style=\"color:#{};\">", self.tags.guides)?;
+        write_guides(io, depth)?;
+        write!(io, "</span>")
+    }
+
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        io.write_all(ROW_START.as_bytes())?;
+        f(io)?;
+        io.write_all(ROW_END.as_bytes())
+    }
+
+    fn fmt_indent(&self, io: &mut dyn io::Write, indent: usize) -> io::Result<()> {
+        for _ in 0..indent {
+            io.write_all(b"&nbsp;&nbsp;")?;
+        }
+        Ok(())
+    }
+}
+
+impl RecordDecorator for EmailRecordDecorator {
+    fn fmt_level(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        self.tags.level(self.level).fmt(io, f)
+    }
+
+    fn fmt_msg(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        self.fmt_text(io, f, &self.tags.message, self.max_message_len)
+    }
+
+    fn fmt_key(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        self.tags.key.fmt(io, f)
+    }
+
+    fn fmt_separator(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        self.tags.separator.fmt(io, f)
+    }
+
+    fn fmt_value(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        self.fmt_text(io, f, &self.tags.value, self.max_value_len)
+    }
+
+    fn fmt_timestamp(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        self.tags.timestamp.fmt(io, f)
+    }
+}
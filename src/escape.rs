@@ -0,0 +1,88 @@
+use std::io;
+use std::io::Write;
+
+/// Writer escaping html special characters before passing them on
+///
+/// Control characters other than tab and line breaks are not allowed in html, even as
+/// character references. C0 controls and DEL are written as their symbols from the
+/// Control Pictures block (`␀`, `␛`, `␡`), and C1 controls and noncharacters as the
+/// replacement character `�`.
+///
+/// Multi-byte characters are recognized within a single write, which always holds whole
+/// characters when writing formatted text.
+pub struct Escape<W: io::Write>(pub W);
+
+/// UTF-8 of U+FFFD REPLACEMENT CHARACTER
+const REPLACEMENT: &[u8] = b"\xef\xbf\xbd";
+
+/// UTF-8 of U+2421 SYMBOL FOR DELETE
+const DELETE: &[u8] = b"\xe2\x90\xa1";
+
+impl<W: io::Write> io::Write for Escape<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        let mut i = 0;
+        while i < buf.len() {
+            let byte = buf[i];
+            let picture;
+            let (escaped, len): (&[u8], usize) = match byte {
+                b'&' => (b"&amp;", 1),
+                b'<' => (b"&lt;", 1),
+                b'>' => (b"&gt;", 1),
+                b'"' => (b"&quot;", 1),
+                b'\'' => (b"&#39;", 1),
+                b'\t' | b'\n' | b'\r' => {
+                    i += 1;
+                    continue;
+                }
+                0x00..=0x1f => {
+                    // U+2400 + byte, in the Control Pictures block
+                    picture = [0xe2, 0x90, 0x80 + byte];
+                    (&picture, 1)
+                }
+                0x7f => (DELETE, 1),
+                _ => {
+                    let len = disallowed_len(&buf[i..]);
+                    if len == 0 {
+                        i += 1;
+                        continue;
+                    }
+                    (REPLACEMENT, len)
+                }
+            };
+            self.0.write_all(&buf[start..i])?;
+            self.0.write_all(escaped)?;
+            i += len;
+            start = i;
+        }
+        self.0.write_all(&buf[start..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Escape `text` into a new string, for html rendered once up front
+pub fn escape(text: &str) -> String {
+    let mut escaped = Vec::new();
+    // Writing to a `Vec` never fails
+    let _ = Escape(&mut escaped).write_all(text.as_bytes());
+    String::from_utf8(escaped).expect("escaped text is UTF-8")
+}
+
+/// Length of the C1 control or noncharacter `bytes` start with, or 0
+fn disallowed_len(bytes: &[u8]) -> usize {
+    match bytes {
+        // U+0080 to U+009F
+        [0xc2, 0x80..=0x9f, ..] => 2,
+        // U+FDD0 to U+FDEF
+        [0xef, 0xb7, 0x90..=0xaf, ..] => 3,
+        // U+FFFE and U+FFFF
+        [0xef, 0xbf, 0xbe..=0xbf, ..] => 3,
+        // U+1FFFE, U+1FFFF, ... U+10FFFF
+        [0xf0..=0xf4, b, 0xbf, 0xbe..=0xbf, ..] if b & 0x0f == 0x0f => 4,
+        _ => 0,
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use context_cache::{ValueStack, shared_levels};
+
+/// Number of contexts remembered
+///
+/// A context evicted from the list gets a new lane, and its headers are printed again.
+const MAX_CONTEXTS: usize = 64;
+
+/// Recently seen logger contexts, each in its own lane
+pub struct Lanes {
+    state: Mutex<LanesState>,
+}
+
+struct LanesState {
+    /// Contexts with their lanes, most recently used first
+    contexts: VecDeque<(usize, ValueStack)>,
+    next_lane: usize,
+}
+
+impl Lanes {
+    pub fn new() -> Self {
+        Lanes {
+            state: Mutex::new(LanesState {
+                contexts: VecDeque::new(),
+                next_lane: 0,
+            }),
+        }
+    }
+
+    /// Get the lane of a context
+    ///
+    /// Returns the lane and the context whose headers were printed last in its place: the
+    /// context itself if it is known, or else the known context sharing the most levels.
+    /// For a new lane, the lane of that closest context comes last.
+    pub fn lane(&self, value_stack: &ValueStack) -> (usize, ValueStack, Option<usize>) {
+        let mut state = self.state.lock().expect("failed to lock lanes");
+
+        let known = state.contexts.iter().position(|(_, stack)| {
+            stack.len() == value_stack.len() && shared_levels(stack, value_stack) == stack.len()
+        });
+        if let Some(position) = known {
+            let context = state.contexts.remove(position).expect("position is in range");
+            let lane = context.0;
+            state.contexts.push_front(context);
+            return (lane, value_stack.clone(), None);
+        }
+
+        let (parent, closest) = state.contexts
+            .iter()
+            .rev()
+            .max_by_key(|(_, stack)| shared_levels(stack, value_stack))
+            .map_or((None, Vec::new()), |(lane, stack)| (Some(*lane), stack.clone()));
+        let lane = state.next_lane;
+        state.next_lane += 1;
+        state.contexts.push_front((lane, value_stack.clone()));
+        state.contexts.truncate(MAX_CONTEXTS);
+        (lane, closest, parent)
+    }
+}
@@ -72,6 +72,7 @@
 //!                     .. slog_html::Style::default()
 //!                 })
 //!                 .build()
+//!                 .unwrap()
 //! #         ).fuse(),
 //! #         o!("version" => env!("CARGO_PKG_VERSION"))
 //! #     );
@@ -81,28 +82,69 @@
 //! ```
 #![warn(missing_docs)]
 
-#[macro_use]
 extern crate slog;
 extern crate slog_stream;
 extern crate chrono;
+extern crate regex;
+extern crate unicode_width;
 
+mod ansi;
+mod async_writer;
+mod buffer;
 mod decorator;
+mod digest;
+mod email;
 mod serializer;
 mod color_palette;
+mod columns;
+mod contrast;
+mod csp;
+mod css;
 mod style;
+mod context_cache;
+mod escape;
+mod lanes;
+mod linkify;
+mod markdown;
+mod merge;
+mod parse;
+mod structured;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timing;
+mod width;
 
 use std::io;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use slog::Record;
+use slog::ser;
 use slog::OwnedKeyValueList;
-use slog_stream::{Decorator, RecordDecorator};
+use slog_stream::Decorator;
 
-use decorator::HtmlDecorator;
+use columns::Columns;
+use context_cache::{ContextCache, NodeValues, ValueStack, shared_levels};
+use decorator::PlainRecordDecorator;
+use lanes::Lanes;
+use timing::Timing;
 use serializer::Serializer;
 use style::StyleTable;
+pub use ansi::AnsiConverter;
+pub use async_writer::{AsyncGuard, AsyncWriter, AsyncWriterBuilder, Overflow};
 pub use style::Style;
 pub use color_palette::ColorPalette;
+pub use contrast::{ContrastWarning, MIN_CONTRAST};
+pub use csp::Csp;
+pub use css::CssError;
+pub use decorator::{DocumentDecorator, HtmlDecorator, MarkupDecorator, Truncation};
+pub use digest::{Digest, DigestBuilder};
+pub use email::EmailDecorator;
+pub use linkify::Linkifier;
+pub use markdown::MarkdownDecorator;
+pub use merge::Merger;
+pub use parse::{ParsedRecord, parse};
+pub use structured::{StructuredValue, TreeNode};
 
 /// Formatting mode
 pub enum FormatMode {
@@ -112,182 +154,492 @@ pub enum FormatMode {
     Full,
 }
 
+/// Rendering of key-value pairs a context header shares with the previous one
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ContextDiff {
+    /// Print shared pairs like changed ones (default)
+    Off,
+    /// Print shared pairs dimmed
+    Dim,
+    /// Leave out shared pairs, unless no pair changed
+    Omit,
+}
+
 /// Html formatter
 pub struct Format<D: Decorator> {
     mode: FormatMode,
-    value_stack: Mutex<Vec<Vec<u8>>>,
+    value_stack: Mutex<ValueStack>,
+    lanes: Option<Lanes>,
+    context_cache: Option<ContextCache>,
+    context_diff: ContextDiff,
+    tree_guides: bool,
+    timing: Option<Timing>,
     decorator: D,
     fn_timestamp: Box<TimestampFn>,
+    structured_values: bool,
+    message_width: Option<usize>,
+    columns: Option<Columns>,
+    format_fn: FormatFn<D>,
 }
 
+/// Function formatting a record with the record decorator of a `Format`
+type FormatFn<D> = fn(&Format<D>,
+                      &mut dyn io::Write,
+                      &Record,
+                      &OwnedKeyValueList,
+                      &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                      -> io::Result<()>;
+
 impl<D: Decorator> Format<D> {
     /// Create a new Html formatter
+    ///
+    /// Lines are written as in the first releases, through the fallbacks of the
+    /// `MarkupDecorator` hooks. Use `with_markup` for a decorator implementing them.
     pub fn new(mode: FormatMode, decorator: D, fn_timestamp: Box<TimestampFn>) -> Self {
         Format {
-            mode: mode,
+            mode,
             value_stack: Mutex::new(Vec::new()),
-            decorator: decorator,
-            fn_timestamp: fn_timestamp,
+            lanes: None,
+            context_cache: None,
+            context_diff: ContextDiff::Off,
+            tree_guides: false,
+            timing: None,
+            decorator,
+            fn_timestamp,
+            structured_values: false,
+            message_width: None,
+            columns: None,
+            format_fn: Format::format_plain,
         }
     }
 
-    fn format_full(&self,
-                   io: &mut io::Write,
-                   record: &Record,
-                   logger_values: &OwnedKeyValueList)
-                   -> io::Result<()> {
-
-        let r_decorator = self.decorator.decorate(record);
+    /// Format a record with the timestamp written by `fn_timestamp`, instead of a new one
+    fn format_with_timestamp(&self,
+                             io: &mut dyn io::Write,
+                             record: &Record,
+                             logger_values: &OwnedKeyValueList,
+                             fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                             -> io::Result<()> {
+        (self.format_fn)(self, io, record, logger_values, fn_timestamp)
+    }
 
-        try!(io.write_all(b"<pre style=\"margin-bottom:-0.5em\">"));
+    /// Format a record with the fallbacks of the markup hooks
+    fn format_plain(&self,
+                    io: &mut dyn io::Write,
+                    record: &Record,
+                    logger_values: &OwnedKeyValueList,
+                    fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                    -> io::Result<()> {
+        let r_decorator = PlainRecordDecorator::new(self.decorator.decorate(record));
+        self.format_decorated(io, &r_decorator, record, logger_values, fn_timestamp)
+    }
 
-        try!(r_decorator.fmt_timestamp(io, &*self.fn_timestamp));
-        try!(r_decorator.fmt_level(io, &|io| write!(io, " {} ", record.level().as_short_str())));
-        try!(r_decorator.fmt_msg(io, &|io| write!(io, "{}", record.msg())));
+    fn format_decorated<R>(&self,
+                           io: &mut dyn io::Write,
+                           r_decorator: &R,
+                           record: &Record,
+                           logger_values: &OwnedKeyValueList,
+                           fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                           -> io::Result<()>
+        where R: MarkupDecorator
+    {
+        match self.mode {
+            FormatMode::Compact => {
+                self.format_compact(io, r_decorator, record, logger_values, fn_timestamp)
+            }
+            FormatMode::Full => {
+                self.format_full(io, r_decorator, record, logger_values, fn_timestamp)
+            }
+        }
+    }
 
-        let mut serializer = Serializer::new(io, r_decorator);
+    fn format_full<R>(&self,
+                      io: &mut dyn io::Write,
+                      r_decorator: &R,
+                      record: &Record,
+                      logger_values: &OwnedKeyValueList,
+                      fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                      -> io::Result<()>
+        where R: MarkupDecorator
+    {
 
-        for (k, v) in logger_values.iter() {
-            try!(serializer.print_comma());
-            try!(v.serialize(record, k, &mut serializer));
-        }
+        r_decorator.fmt_line(io, &|io| {
+            r_decorator.fmt_timestamp(io, fn_timestamp)?;
+            self.print_timing(io, r_decorator, logger_values)?;
+            r_decorator.fmt_level(io,
+                                       &|io| write!(io, " {} ", record.level().as_short_str()))?;
+            // Aligned messages are measured, so they are formatted only once
+            let msg = self.message_width.map(|_| record.msg().to_string());
+            match msg {
+                Some(ref msg) => r_decorator.fmt_msg(io, &|io| io.write_all(msg.as_bytes()))?,
+                None => r_decorator.fmt_msg(io, &|io| write!(io, "{}", record.msg()))?,
+            }
 
-        for &(k, v) in record.values().iter() {
-            try!(serializer.print_comma());
-            try!(v.serialize(record, k, &mut serializer));
-        }
+            if logger_values.iter().next().is_none() && record.values().is_empty() {
+                return Ok(());
+            }
+            r_decorator.fmt_pairs(io, true, &|io| {
+                if let (Some(width), Some(msg)) = (self.message_width, msg.as_ref()) {
+                    let padding = width.saturating_sub(r_decorator.msg_width(msg));
+                    r_decorator.fmt_padding(io, padding)?;
+                }
+                let mut serializer = Serializer::new(io, r_decorator, self.structured_values);
+                if let Some(ref columns) = self.columns {
+                    let mut serializer = serializer.measured();
+                    return self.print_columns(&mut serializer, record, logger_values, columns);
+                }
+                let mut clean = true;
 
-        let (mut io, _) = serializer.finish();
+                for (k, v) in logger_values.iter() {
+                    if !clean {
+                        serializer.print_comma()?;
+                    }
+                    v.serialize(record, k, &mut serializer)?;
+                    clean = false;
+                }
 
-        io.write_all(b"</pre>\n")
+                for &(k, v) in record.values().iter() {
+                    if !clean {
+                        serializer.print_comma()?;
+                    }
+                    v.serialize(record, k, &mut serializer)?;
+                    clean = false;
+                }
+                Ok(())
+            })
+        })
     }
 
-    fn format_compact(&self,
-                      io: &mut io::Write,
-                      record: &Record,
-                      logger_values: &OwnedKeyValueList)
-                      -> io::Result<()> {
+    fn format_compact<R>(&self,
+                         io: &mut dyn io::Write,
+                         r_decorator: &R,
+                         record: &Record,
+                         logger_values: &OwnedKeyValueList,
+                         fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                         -> io::Result<()>
+        where R: MarkupDecorator
+    {
 
-        let mut value_stack = self.value_stack.lock().expect("failed to lock value_stack");
-        let mut record_value_stack = try!(self.record_value_stack(record, logger_values));
-        record_value_stack.reverse();
+        let record_value_stack = self.record_value_stack(r_decorator, record, logger_values)?;
         let indent = record_value_stack.len();
 
-        let mut changed = false;
-        for i in 0..record_value_stack.len() {
-            if value_stack.len() <= i || value_stack[i] != record_value_stack[i] {
-                changed = true;
+        let (lane, previous, parent) = match self.lanes {
+            Some(ref lanes) => {
+                let (lane, previous, parent) = lanes.lane(&record_value_stack);
+                (Some(lane), previous, parent)
             }
-
-            if changed {
-                try!(io.write_all(b"<pre style=\"margin-bottom:-0.5em\">"));
-                try!(self.print_indent(io, i));
-                try!(io.write_all(&record_value_stack[i]));
-                try!(io.write_all(b"</pre>\n"));
+            None => {
+                let mut value_stack = self.value_stack.lock().expect("failed to lock value_stack");
+                let previous = value_stack.clone();
+                if shared_levels(&value_stack, &record_value_stack) != indent ||
+                   value_stack.len() != indent {
+                    *value_stack = record_value_stack.clone();
+                }
+                (None, previous, None)
             }
-        }
-        if changed || value_stack.len() != record_value_stack.len() {
-            *value_stack = record_value_stack;
-        }
+        };
+        let changed = shared_levels(&previous, &record_value_stack);
 
-        let r_decorator = self.decorator.decorate(record);
+        for (i, values) in record_value_stack.iter().enumerate().skip(changed) {
+            r_decorator.fmt_line(io, &|io| {
+                if let Some(lane) = lane {
+                    r_decorator.fmt_lane(io, lane, parent)?;
+                }
+                self.print_indent(io, r_decorator, i)?;
+                self.print_node_values(io, r_decorator, values, previous.get(i))
+            })?;
+        }
 
-        try!(io.write_all(b"<pre style=\"margin-bottom:-0.5em\">"));
+        r_decorator.fmt_line(io, &|io| {
+            if let Some(lane) = lane {
+                r_decorator.fmt_lane(io, lane, parent)?;
+            }
+            self.print_indent(io, r_decorator, indent)?;
+            r_decorator.fmt_timestamp(io, fn_timestamp)?;
+            self.print_timing(io, r_decorator, logger_values)?;
+            r_decorator.fmt_level(io,
+                                       &|io| write!(io, " {} ", record.level().as_short_str()))?;
+            r_decorator.fmt_msg(io, &|io| write!(io, "{}", record.msg()))?;
 
-        try!(self.print_indent(io, indent));
-        try!(r_decorator.fmt_timestamp(io, &*self.fn_timestamp));
-        try!(r_decorator.fmt_level(io, &|io| write!(io, " {} ", record.level().as_short_str())));
-        try!(r_decorator.fmt_msg(io, &|io| write!(io, "{}", record.msg())));
+            if record.values().is_empty() {
+                return Ok(());
+            }
+            r_decorator.fmt_pairs(io, true, &|io| {
+                let mut serializer = Serializer::new(io, r_decorator, self.structured_values);
+                for (i, &(k, v)) in record.values().iter().enumerate() {
+                    if i > 0 {
+                        serializer.print_comma()?;
+                    }
+                    v.serialize(record, k, &mut serializer)?;
+                }
+                Ok(())
+            })
+        })
+    }
 
-        let mut serializer = Serializer::new(io, r_decorator);
+    /// Get formatted values of every `logger_values` node, starting at the root
+    fn record_value_stack<R>(&self,
+                             r_decorator: &R,
+                             record: &slog::Record,
+                             logger_values: &slog::OwnedKeyValueList)
+                             -> io::Result<ValueStack>
+        where R: MarkupDecorator
+    {
 
-        for &(k, v) in record.values().iter() {
-            try!(serializer.print_comma());
-            try!(v.serialize(record, k, &mut serializer));
+        let mut value_stack = Vec::new();
+        let mut node = Some(logger_values);
+        while let Some(logger_values) = node {
+            if logger_values.values().is_some() {
+                let values = match self.context_cache {
+                    Some(ref cache) => {
+                        cache.get(logger_values,
+                                  || self.node_values(r_decorator, record, logger_values))?
+                    }
+                    None => Arc::new(self.node_values(r_decorator, record, logger_values)?),
+                };
+                value_stack.push(values);
+            }
+            // The only way to walk up the nodes in slog 1.x
+            #[allow(deprecated)]
+            let parent = logger_values.parent();
+            node = parent.as_ref();
         }
+        value_stack.reverse();
+
+        Ok(value_stack)
+    }
+
+    /// Get formatted key-value pairs of the head node of `logger_values_ref`
+    fn node_values<R>(&self,
+                      r_decorator: &R,
+                      record: &slog::Record,
+                      logger_values_ref: &slog::OwnedKeyValueList)
+                      -> io::Result<NodeValues>
+        where R: MarkupDecorator
+    {
 
-        let (mut io, _) = serializer.finish();
+        let mut pairs = Vec::new();
 
-        io.write_all(b"</pre>\n")
+        let mut logger_values = logger_values_ref.values();
+        while let Some(values) = logger_values {
+            let (k, v) = values.head();
+            let buf: Vec<u8> = Vec::with_capacity(64);
+            let mut serializer = Serializer::new(buf, r_decorator, self.structured_values);
+            v.serialize(record, k, &mut serializer)?;
+            pairs.push((k, serializer.finish()));
+            logger_values = values.tail();
+        }
+
+        Ok(NodeValues { pairs })
     }
 
-    /// Get formatted record_value_stack from `logger_values_ref`
-    fn record_value_stack(&self,
-                          record: &slog::Record,
-                          logger_values_ref: &slog::OwnedKeyValueList)
-                          -> io::Result<Vec<Vec<u8>>> {
+    /// Print the indentation of a line in compact mode, as guide lines if enabled
+    fn print_indent<R>(&self,
+                       io: &mut dyn io::Write,
+                       r_decorator: &R,
+                       depth: usize)
+                       -> io::Result<()>
+        where R: MarkupDecorator
+    {
+        if self.tree_guides {
+            r_decorator.fmt_guides(io, depth)
+        } else {
+            r_decorator.fmt_indent(io, depth)
+        }
+    }
 
-        let mut value_stack = if let Some(logger_values) = logger_values_ref.values() {
-            let r_decorator = self.decorator.decorate(record);
-            let buf: Vec<u8> = Vec::with_capacity(128);
-            let mut serializer = Serializer::new(buf, r_decorator);
+    /// Print the formatted key-value pairs of a context header
+    ///
+    /// Pairs that are the same in `previous`, the header printed last at the same level,
+    /// are dimmed or left out depending on `context_diff`.
+    fn print_node_values<R>(&self,
+                            io: &mut dyn io::Write,
+                            r_decorator: &R,
+                            values: &NodeValues,
+                            previous: Option<&Arc<NodeValues>>)
+                            -> io::Result<()>
+        where R: MarkupDecorator
+    {
+        let unchanged = |&(key, ref formatted): &(&str, Vec<u8>)| match previous {
+            Some(previous) if self.context_diff != ContextDiff::Off => {
+                previous.contains(key, formatted)
+            }
+            _ => false,
+        };
+        let omit = self.context_diff == ContextDiff::Omit &&
+                   !values.pairs.iter().all(&unchanged);
 
+        r_decorator.fmt_pairs(io, false, &|io| {
             let mut clean = true;
-            let mut logger_values = logger_values;
-            loop {
-                let (k, v) = logger_values.head();
+            for pair in &values.pairs {
+                let unchanged = unchanged(pair);
+                if unchanged && omit {
+                    continue;
+                }
                 if !clean {
-                    try!(serializer.print_comma());
+                    r_decorator.fmt_pair_separator(io)?;
                 }
-                try!(v.serialize(record, k, &mut serializer));
-                clean = false;
-                logger_values = if let Some(v) = logger_values.tail() {
-                    v
+                if unchanged {
+                    r_decorator.fmt_unchanged(io, &|io| io.write_all(&pair.1))?;
                 } else {
-                    break;
+                    io.write_all(&pair.1)?;
                 }
+                clean = false;
             }
-            let (buf, _) = serializer.finish();
-            vec![buf]
-        } else {
-            Vec::new()
-        };
+            Ok(())
+        })
+    }
+
+    /// Print the key-value pairs of a record in full mode, lining up the pairs with keys
+    /// seen in earlier records
+    ///
+    /// The pairs with a column come first, padded to its width, and the other pairs follow
+    /// in order. A column without a pair in the record is left blank.
+    fn print_columns<R>(&self,
+                        serializer: &mut Serializer<&mut dyn io::Write, R>,
+                        record: &Record,
+                        logger_values: &OwnedKeyValueList,
+                        columns: &Columns)
+                        -> io::Result<()>
+        where R: MarkupDecorator
+    {
+        let pairs: Vec<(&str, &dyn ser::Serialize)> = logger_values.iter()
+            .map(|(k, v)| (k, v as &dyn ser::Serialize))
+            .chain(record.values().iter().map(|&(k, v)| (k, v)))
+            .collect();
+        let keys: Vec<&str> = pairs.iter().map(|&(k, _)| k).collect();
+        let layout = columns.layout(&keys);
 
-        if let Some(ref parent) = *logger_values_ref.parent() {
-            let mut value = try!(self.record_value_stack(record, parent));
-            value_stack.append(&mut value);
+        let mut clean = true;
+        let mut padding = 0;
+        for (column, &(pair, width)) in layout.columns.iter().enumerate() {
+            let (k, v) = match pair {
+                Some(i) => pairs[i],
+                None => {
+                    // Blank, including the separator after it
+                    padding += width + 2;
+                    continue;
+                }
+            };
+            if !clean {
+                serializer.print_comma()?;
+            }
+            serializer.print_padding(padding)?;
+            v.serialize(record, k, serializer)?;
+            padding = columns.widen(column, serializer.width()) - serializer.width();
+            clean = false;
         }
 
-        Ok(value_stack)
+        for &i in &layout.others {
+            let (k, v) = pairs[i];
+            if !clean {
+                serializer.print_comma()?;
+                serializer.print_padding(padding)?;
+                padding = 0;
+            }
+            v.serialize(record, k, serializer)?;
+            clean = false;
+        }
+        Ok(())
     }
 
-    fn print_indent(&self, io: &mut io::Write, indent: usize) -> io::Result<()> {
-        for _ in 0..indent {
-            try!(write!(io, "  "));
+    /// Print the enabled time columns
+    fn print_timing<R>(&self,
+                       io: &mut dyn io::Write,
+                       r_decorator: &R,
+                       logger_values: &OwnedKeyValueList)
+                       -> io::Result<()>
+        where R: MarkupDecorator
+    {
+        let timing = match self.timing {
+            Some(ref timing) => timing,
+            None => return Ok(()),
+        };
+        let record_timing = timing.record(logger_values);
+
+        if let Some(elapsed) = record_timing.elapsed {
+            let elapsed = timing::fmt_duration(elapsed);
+            r_decorator.fmt_duration(io, &|io| write!(io, " +{:>9}", elapsed), false)?;
+        }
+        let deltas = [("\u{394}", record_timing.delta), ("\u{394}ctx ", record_timing.context_delta)];
+        for &(label, delta) in deltas.iter() {
+            if let Some(delta) = delta {
+                let text = delta.map_or_else(|| "-".to_owned(), timing::fmt_duration);
+                let gap = delta.is_some_and(|delta| timing.is_gap(delta));
+                r_decorator.fmt_duration(io, &|io| write!(io, " {}{:>8}", label, text), gap)?;
+            }
         }
         Ok(())
     }
 }
 
+impl<D: Decorator> Format<D>
+    where D::RecordDecorator: MarkupDecorator
+{
+    /// Create a new Html formatter writing lines through the markup hooks of `decorator`
+    pub fn with_markup(mode: FormatMode, decorator: D, fn_timestamp: Box<TimestampFn>) -> Self {
+        Format { format_fn: Format::format_markup, ..Format::new(mode, decorator, fn_timestamp) }
+    }
+
+    /// Format a record with the markup hooks of the record decorator
+    fn format_markup(&self,
+                     io: &mut dyn io::Write,
+                     record: &Record,
+                     logger_values: &OwnedKeyValueList,
+                     fn_timestamp: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        let r_decorator = self.decorator.decorate(record);
+        self.format_decorated(io, &r_decorator, record, logger_values, fn_timestamp)
+    }
+}
+
+impl<D: DocumentDecorator> Format<D> {
+    /// Write the start of a document, to come before the first record
+    pub fn write_prologue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        self.decorator.write_prologue(io)
+    }
+
+    /// Write the end of a document, to come after the last record
+    pub fn write_epilogue(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        self.decorator.write_epilogue(io)
+    }
+
+    /// Write the stylesheet of output styled by classes, in Content Security Policy or
+    /// fragment mode
+    ///
+    /// Serve it at the URL of `Csp::Stylesheet`, or write it into a `<style>` element in the
+    /// head of the page embedding a fragment. Writes nothing for output styled inline.
+    pub fn write_stylesheet(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        self.decorator.write_stylesheet(io)
+    }
+}
+
 impl<D: Decorator> slog_stream::Format for Format<D> {
     fn format(&self,
-              io: &mut io::Write,
+              io: &mut dyn io::Write,
               record: &Record,
               logger_values: &OwnedKeyValueList)
               -> io::Result<()> {
-        match self.mode {
-            FormatMode::Compact => self.format_compact(io, record, logger_values),
-            FormatMode::Full => self.format_full(io, record, logger_values),
-        }
+        self.format_with_timestamp(io, record, logger_values, &*self.fn_timestamp)
     }
 }
 
 /// Timestamp function type
-pub type TimestampFn = Fn(&mut io::Write) -> io::Result<()> + Send + Sync;
+pub type TimestampFn = dyn Fn(&mut dyn io::Write) -> io::Result<()> + Send + Sync;
 
-const TIMESTAMP_FORMAT: &'static str = "%b %d %H:%M:%S%.3f";
+const TIMESTAMP_FORMAT: &str = "%b %d %H:%M:%S%.3f";
 
 /// Default local timestamp function used by `Format`
 ///
 /// The exact format used, is still subject to change.
-pub fn timestamp_local(io: &mut io::Write) -> io::Result<()> {
+pub fn timestamp_local(io: &mut dyn io::Write) -> io::Result<()> {
     write!(io, "{}", chrono::Local::now().format(TIMESTAMP_FORMAT))
 }
 
 /// Default UTC timestamp function used by `Format`
 ///
 /// The exact format used, is still subject to change.
-pub fn timestamp_utc(io: &mut io::Write) -> io::Result<()> {
+pub fn timestamp_utc(io: &mut dyn io::Write) -> io::Result<()> {
     write!(io, "{}", chrono::UTC::now().format(TIMESTAMP_FORMAT))
 }
 
@@ -296,7 +648,27 @@ pub struct FormatBuilder {
     mode: FormatMode,
     color_palette: ColorPalette,
     style: StyleTable,
+    background: &'static str,
+    adjust_contrast: bool,
     fn_timestamp: Box<TimestampFn>,
+    structured_values: bool,
+    message_width: Option<usize>,
+    columns: bool,
+    linkifier: Option<Linkifier>,
+    csp: Option<Csp>,
+    fragment: Option<String>,
+    collapse_lines: Option<usize>,
+    max_message_len: Option<usize>,
+    max_value_len: Option<usize>,
+    truncation: Truncation,
+    context_cache: bool,
+    context_lanes: bool,
+    context_diff: ContextDiff,
+    tree_guides: bool,
+    elapsed_time: bool,
+    delta_time: bool,
+    context_delta_time: bool,
+    gap_threshold: Option<Duration>,
 }
 
 impl FormatBuilder {
@@ -306,7 +678,27 @@ impl FormatBuilder {
             mode: FormatMode::Full,
             color_palette: ColorPalette::default(),
             style: StyleTable::default(),
+            background: "ffffff",
+            adjust_contrast: false,
             fn_timestamp: Box::new(timestamp_local),
+            structured_values: false,
+            message_width: None,
+            columns: false,
+            linkifier: None,
+            csp: None,
+            fragment: None,
+            collapse_lines: None,
+            max_message_len: None,
+            max_value_len: None,
+            truncation: Truncation::Expand,
+            context_cache: false,
+            context_lanes: false,
+            context_diff: ContextDiff::Off,
+            tree_guides: false,
+            elapsed_time: false,
+            delta_time: false,
+            context_delta_time: false,
+            gap_threshold: None,
         }
     }
 
@@ -364,6 +756,57 @@ impl FormatBuilder {
         self
     }
 
+    /// Use custom style for time columns
+    pub fn duration_style(mut self, style: Style) -> Self {
+        self.style.duration = style;
+        self
+    }
+
+    /// Use custom style for time columns above the gap threshold
+    pub fn gap_style(mut self, style: Style) -> Self {
+        self.style.gap = style;
+        self
+    }
+
+    /// Background color the output is shown on, white by default
+    ///
+    /// Colors are checked against it by `contrast_warnings` and `adjust_contrast`. The
+    /// output itself does not set a background.
+    pub fn background(mut self, color: &'static str) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Darken or lighten colors of the palette, styles, context lanes and tree guides to a
+    /// contrast of at least `MIN_CONTRAST` against the background
+    ///
+    /// Level colors then keep their hue while staying readable. Colors in custom CSS are
+    /// not adjusted.
+    pub fn adjust_contrast(mut self, enabled: bool) -> Self {
+        self.adjust_contrast = enabled;
+        self
+    }
+
+    /// Colors of the palette, styles, context lanes and tree guides with a contrast below
+    /// `MIN_CONTRAST` against the background
+    ///
+    /// The default colors pass against a white background, lighter custom colors may not.
+    /// Colors in custom CSS are not checked.
+    ///
+    /// ```
+    /// let builder = slog_html::new();
+    /// for warning in builder.contrast_warnings() {
+    ///     println!("{}", warning);
+    /// }
+    /// assert!(builder.adjust_contrast(true).contrast_warnings().is_empty());
+    /// ```
+    pub fn contrast_warnings(&self) -> Vec<ContrastWarning> {
+        if self.adjust_contrast {
+            return Vec::new();
+        }
+        self.style.check_contrast(&self.color_palette, self.background)
+    }
+
     /// Use the UTC time zone for the timestamp
     pub fn use_utc_timestamp(mut self) -> Self {
         self.fn_timestamp = Box::new(timestamp_utc);
@@ -378,19 +821,261 @@ impl FormatBuilder {
 
     /// Provide a custom function to generate the timestamp
     pub fn use_custom_timestamp<F>(mut self, f: F) -> Self
-        where F: Fn(&mut io::Write) -> io::Result<()> + 'static + Send + Sync
+        where F: Fn(&mut dyn io::Write) -> io::Result<()> + 'static + Send + Sync
     {
         self.fn_timestamp = Box::new(f);
         self
     }
 
+    /// Show the time since the formatter was built
+    pub fn elapsed_time(mut self, enabled: bool) -> Self {
+        self.elapsed_time = enabled;
+        self
+    }
+
+    /// Show the time since the previous record
+    pub fn delta_time(mut self, enabled: bool) -> Self {
+        self.delta_time = enabled;
+        self
+    }
+
+    /// Show the time since the previous record of the same logger
+    pub fn context_delta_time(mut self, enabled: bool) -> Self {
+        self.context_delta_time = enabled;
+        self
+    }
+
+    /// Highlight times between records longer than `threshold`, using the gap style
+    pub fn gap_threshold(mut self, threshold: Duration) -> Self {
+        self.gap_threshold = Some(threshold);
+        self
+    }
+
+    /// Pad messages to `width` columns in full mode, so that the key-value pairs of all
+    /// records start in the same column
+    ///
+    /// Widths are measured as displayed in a monospace font, with wide characters like CJK
+    /// ideographs and emoji taking up two columns. Longer messages are not cut, see
+    /// `max_message_len`.
+    pub fn message_width(mut self, width: usize) -> Self {
+        self.message_width = Some(width);
+        self
+    }
+
+    /// Line up key-value pairs in columns in full mode
+    ///
+    /// A key seen in an earlier record is given a column, and its pairs are printed first,
+    /// in the order of the columns and padded to their widest pair so far. Other pairs
+    /// follow in their own order. Combine with `message_width` for the columns to start at
+    /// the same place in every record.
+    pub fn columns(mut self, enabled: bool) -> Self {
+        self.columns = enabled;
+        self
+    }
+
+    /// Render JSON and pretty-printed `Debug` values as collapsible trees
+    ///
+    /// The one-line summary of a structured value stays visible, its details are
+    /// expanded on click. Every string value is checked for structure, and formatted
+    /// values like `format_args!` are formatted into a buffer first.
+    pub fn structured_values(mut self, enabled: bool) -> Self {
+        self.structured_values = enabled;
+        self
+    }
+
+    /// Turn URLs and other patterns in messages and values into links
+    pub fn linkify(mut self, linkifier: Linkifier) -> Self {
+        self.linkifier = Some(linkifier);
+        self
+    }
+
+    /// Style html output by a stylesheet, for a strict Content Security Policy
+    ///
+    /// Elements have classes instead of `style` attributes, and the stylesheet is either
+    /// inline with a nonce or linked, see `Csp`. Only applies to `build`, as email clients
+    /// need inline styles.
+    pub fn csp(mut self, csp: Csp) -> Self {
+        self.csp = Some(csp);
+        self
+    }
+
+    /// Value of the `Content-Security-Policy` header to serve the output with
+    ///
+    /// `None` without Content Security Policy mode.
+    pub fn csp_header(&self) -> Option<String> {
+        self.csp.as_ref().map(Csp::header)
+    }
+
+    /// Write a fragment to embed into other pages, instead of a whole document
+    ///
+    /// The prologue opens a `<div class="prefix">` root element, and the epilogue closes it.
+    /// Elements are styled by classes like `prefix-line`, and every rule of the stylesheet
+    /// is scoped to the root element, so that fragments with different prefixes can be
+    /// styled independently on one page. Combines with `csp`, and only applies to `build`.
+    ///
+    /// Stylesheets are only allowed in the head of a page, so the fragment has none of its
+    /// own. Write the one of `Format::write_stylesheet` into a `<style>` element in the head
+    /// of the page, with the nonce of `Csp::Nonce` if any, or serve it at the URL of
+    /// `Csp::Stylesheet`.
+    ///
+    /// `prefix` must be a CSS identifier made of ASCII letters, digits, `-` and `_`, or
+    /// `build` fails.
+    pub fn fragment(mut self, prefix: &str) -> Self {
+        self.fragment = Some(prefix.to_owned());
+        self
+    }
+
+    /// Collapse multi-line messages and values after `lines` lines
+    ///
+    /// The remaining lines are expanded on click. Without this option, all lines are shown.
+    pub fn collapse_lines(mut self, lines: usize) -> Self {
+        self.collapse_lines = Some(lines);
+        self
+    }
+
+    /// Truncate messages longer than `len` bytes
+    pub fn max_message_len(mut self, len: usize) -> Self {
+        self.max_message_len = Some(len);
+        self
+    }
+
+    /// Truncate values longer than `len` bytes
+    pub fn max_value_len(mut self, len: usize) -> Self {
+        self.max_value_len = Some(len);
+        self
+    }
+
+    /// Keep or drop the truncated part of messages and values
+    ///
+    /// Truncated text is followed by a marker with the number of bytes cut off. By
+    /// default, the full text is expanded on click (`Truncation::Expand`).
+    pub fn truncation(mut self, truncation: Truncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Mark records with a lane per logger context in compact mode
+    ///
+    /// Context headers are printed once, when a context is first seen, and every record
+    /// is marked with the lane of its context. Records of loggers used concurrently can
+    /// then be told apart without reprinting the headers each time the context changes.
+    pub fn context_lanes(mut self, enabled: bool) -> Self {
+        self.context_lanes = enabled;
+        self
+    }
+
+    /// Highlight the changes of context headers in compact mode
+    ///
+    /// A header is compared pair by pair with the previous header at the same level, so
+    /// that only the changed key-value pairs stand out.
+    pub fn context_diff(mut self, context_diff: ContextDiff) -> Self {
+        self.context_diff = context_diff;
+        self
+    }
+
+    /// Draw the tree of loggers with guide lines in compact mode
+    ///
+    /// Every child logger's context header is a node connected to its parent's, and its
+    /// records are nested under it, instead of being indented with spaces.
+    pub fn tree_guides(mut self, enabled: bool) -> Self {
+        self.tree_guides = enabled;
+        self
+    }
+
+    /// Serialize the values of each logger only once in compact mode
+    ///
+    /// Values computed lazily from the record (closures in `o!`) are then evaluated for
+    /// the first record of each logger only, so only enable the cache if the context of
+    /// your loggers holds no lazy values, or if their first value is good enough.
+    pub fn context_cache(mut self, enabled: bool) -> Self {
+        self.context_cache = enabled;
+        self
+    }
+
     /// Build Html formatter
-    pub fn build(self) -> Format<HtmlDecorator> {
+    ///
+    /// Fails if a color is not a hexadecimal color code, if the custom CSS of a style is
+    /// not valid, see `Style::custom`, or if the prefix of `fragment` is not an identifier.
+    pub fn build(mut self) -> Result<Format<HtmlDecorator>, CssError> {
+        self.style.validate(&self.color_palette, self.background)?;
+        if let Some(ref prefix) = self.fragment {
+            css::check_identifier(prefix)?;
+        }
+        let decorator = HtmlDecorator::new(self.color_palette, self.style_table())
+            .linkifier(self.linkifier.take())
+            .csp(self.csp.take())
+            .fragment(self.fragment.take())
+            .collapse_lines(self.collapse_lines)
+            .truncate(self.max_message_len, self.max_value_len, self.truncation);
+        Ok(self.build_with(decorator))
+    }
+
+    /// Build Markdown formatter
+    ///
+    /// Colors, styles, links, collapsing and truncation only apply to html.
+    pub fn build_markdown(self) -> Format<MarkdownDecorator> {
+        self.build_with(MarkdownDecorator::new())
+    }
+
+    /// Build html formatter for email bodies
+    ///
+    /// Records are rows of a layout table with inline styles only. Multi-line text is not
+    /// collapsed, and truncated text is dropped. Fails like `build` on invalid colors and
+    /// custom CSS.
+    pub fn build_email(mut self) -> Result<Format<EmailDecorator>, CssError> {
+        self.style.validate(&self.color_palette, self.background)?;
+        let decorator = EmailDecorator::new(self.color_palette, self.style_table())
+            .linkifier(self.linkifier.take())
+            .truncate(self.max_message_len, self.max_value_len);
+        Ok(self.build_with(decorator))
+    }
+
+    /// Styles with the contrast adjustment, if enabled
+    fn style_table(&self) -> StyleTable {
+        let mut style = self.style;
+        if self.adjust_contrast {
+            style.adjust_contrast = Some(self.background);
+        }
+        style
+    }
+
+    fn build_with<D>(self, decorator: D) -> Format<D>
+        where D: Decorator,
+              D::RecordDecorator: MarkupDecorator
+    {
         Format {
             mode: self.mode,
             value_stack: Mutex::new(Vec::new()),
-            decorator: HtmlDecorator::new(self.color_palette, self.style),
+            lanes: if self.context_lanes {
+                Some(Lanes::new())
+            } else {
+                None
+            },
+            context_cache: if self.context_cache {
+                Some(ContextCache::new())
+            } else {
+                None
+            },
+            context_diff: self.context_diff,
+            tree_guides: self.tree_guides,
+            timing: if self.elapsed_time || self.delta_time || self.context_delta_time {
+                Some(Timing::new(self.elapsed_time,
+                                 self.delta_time,
+                                 self.context_delta_time,
+                                 self.gap_threshold))
+            } else {
+                None
+            },
+            decorator,
             fn_timestamp: self.fn_timestamp,
+            structured_values: self.structured_values,
+            message_width: self.message_width,
+            columns: if self.columns {
+                Some(Columns::new())
+            } else {
+                None
+            },
+            format_fn: Format::format_markup,
         }
     }
 }
@@ -408,5 +1093,22 @@ pub fn new() -> FormatBuilder {
 
 /// Default html `Format`
 pub fn default() -> Format<HtmlDecorator> {
-    FormatBuilder::new().build()
+    FormatBuilder::new().build().expect("default styles are valid")
+}
+
+/// Create new `AsyncWriterBuilder` writing records of `format` to `io` on a background thread
+pub fn async_writer<W, D>(io: W, format: Format<D>) -> AsyncWriterBuilder<W, D>
+    where W: io::Write + Send + 'static,
+          D: DocumentDecorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    AsyncWriterBuilder::new(io, format)
+}
+
+/// Create new `DigestBuilder` collecting records of `format` into a single document
+pub fn digest<D>(format: Format<D>) -> DigestBuilder<D>
+    where D: DocumentDecorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    DigestBuilder::new(format)
 }
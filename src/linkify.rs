@@ -0,0 +1,165 @@
+use std::io;
+use std::io::Write;
+
+use regex::{self, Regex};
+
+use escape::Escape;
+
+/// Pattern for URLs with a scheme that is safe to link to
+const URL_PATTERN: &str = r#"\b(?:https?|ftp|file)://[^\s<>"']+"#;
+
+/// Pattern for absolute file paths with at least two components
+const PATH_PATTERN: &str = r#"(?:^|[\s(\[='"])(/[\w.~+-]+(?:/[\w.~+-]+)+/?)"#;
+
+/// Trailing characters that end a sentence rather than a URL
+const URL_TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}'];
+
+/// Turns URLs, file paths and custom patterns in messages and values into links
+///
+/// ```
+/// # extern crate slog_html;
+/// # fn main() {
+/// let linkifier = slog_html::Linkifier::new()
+///     .file_paths(true)
+///     .rule(r"\b[A-Z]+-\d+\b", "https://issues.example.com/browse/$0")
+///     .unwrap();
+///
+/// let format = slog_html::new().linkify(linkifier).build().unwrap();
+/// # let _ = format;
+/// # }
+/// ```
+pub struct Linkifier {
+    urls: Option<Regex>,
+    paths: Option<Regex>,
+    rules: Vec<(Regex, String)>,
+}
+
+impl Linkifier {
+    /// New `Linkifier` linking URLs
+    pub fn new() -> Self {
+        Linkifier {
+            urls: Some(Regex::new(URL_PATTERN).expect("invalid url pattern")),
+            paths: None,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Link `http`, `https`, `ftp` and `file` URLs (default)
+    pub fn urls(mut self, enabled: bool) -> Self {
+        self.urls = if enabled {
+            Some(Regex::new(URL_PATTERN).expect("invalid url pattern"))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Link absolute file paths, using `file://` URLs
+    pub fn file_paths(mut self, enabled: bool) -> Self {
+        self.paths = if enabled {
+            Some(Regex::new(PATH_PATTERN).expect("invalid path pattern"))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Link every match of `pattern` to `template`
+    ///
+    /// `template` may refer to capture groups of `pattern`, e.g. `$0` for the whole match,
+    /// `$1` or `${name}`. Rules are tried in the order they were added, after URLs and file
+    /// paths.
+    pub fn rule(mut self, pattern: &str, template: &str) -> Result<Self, regex::Error> {
+        let regex = Regex::new(pattern)?;
+        self.rules.push((regex, template.to_owned()));
+        Ok(self)
+    }
+
+    /// Find all links in `text`, ordered by position and without overlaps
+    fn links(&self, text: &str) -> Vec<Link> {
+        let mut links = Vec::new();
+        if let Some(ref urls) = self.urls {
+            for m in urls.find_iter(text) {
+                let url = m.as_str().trim_end_matches(URL_TRAILING);
+                links.push(Link::new(m.start(), m.start() + url.len(), url.to_owned()));
+            }
+        }
+        if let Some(ref paths) = self.paths {
+            for caps in paths.captures_iter(text) {
+                let m = caps.get(1).expect("path pattern has a group");
+                let path = m.as_str().trim_end_matches('.');
+                let href = format!("file://{}", path);
+                links.push(Link::new(m.start(), m.start() + path.len(), href));
+            }
+        }
+        for (regex, template) in &self.rules {
+            for caps in regex.captures_iter(text) {
+                let m = caps.get(0).expect("group 0 is always present");
+                let mut href = String::new();
+                caps.expand(template, &mut href);
+                links.push(Link::new(m.start(), m.end(), href));
+            }
+        }
+
+        links.sort_by_key(|link| link.start);
+        let mut end = 0;
+        links.retain(|link| {
+            let keep = link.start >= end && link.end > link.start && is_safe_href(&link.href);
+            if keep {
+                end = link.end;
+            }
+            keep
+        });
+        links
+    }
+
+    /// Write `text` as escaped html with its links as `<a>` elements
+    pub fn write_html(&self, io: &mut dyn io::Write, text: &str) -> io::Result<()> {
+        let mut pos = 0;
+        for link in self.links(text) {
+            write!(Escape(&mut *io), "{}", &text[pos..link.start])?;
+            write!(io, "<a href=\"")?;
+            write!(Escape(&mut *io), "{}", link.href)?;
+            write!(io, "\">")?;
+            write!(Escape(&mut *io), "{}", &text[link.start..link.end])?;
+            write!(io, "</a>")?;
+            pos = link.end;
+        }
+        write!(Escape(io), "{}", &text[pos..])
+    }
+}
+
+impl Default for Linkifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Link {
+    start: usize,
+    end: usize,
+    href: String,
+}
+
+impl Link {
+    fn new(start: usize, end: usize, href: String) -> Self {
+        Link {
+            start,
+            end,
+            href,
+        }
+    }
+}
+
+/// Reject schemes that execute code when a link is followed
+fn is_safe_href(href: &str) -> bool {
+    if !href.contains(':') {
+        return true;
+    }
+    let scheme: String = href.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take_while(|&c| c != ':')
+        .flat_map(char::to_lowercase)
+        .collect();
+    !["javascript", "vbscript", "data"].contains(&scheme.as_str())
+}
@@ -0,0 +1,316 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+
+use slog::{Level, Record};
+use slog_stream::{Decorator, RecordDecorator};
+
+use buffer::with_buffer;
+use decorator::{DocumentDecorator, MarkupDecorator};
+
+/// GitHub flavored Markdown decorator
+///
+/// Every line is a paragraph, with the level as a badge and the message in bold. Values are
+/// code spans, and the key-value pairs of a record follow it as a table. Context headers in
+/// compact mode keep their pairs inline.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate slog;
+/// # extern crate slog_html;
+/// # extern crate slog_stream;
+/// #
+/// # use slog::DrainExt;
+/// #
+/// # use std::io;
+/// #
+/// # fn main() {
+/// let format = slog_html::new().full().build_markdown();
+///
+/// let log = slog::Logger::root(slog_stream::stream(io::stdout(), format).fuse(), o!());
+/// info!(log, "request served"; "path" => "/index.html", "status" => 200);
+/// # }
+/// ```
+pub struct MarkdownDecorator;
+
+impl MarkdownDecorator {
+    /// New `MarkdownDecorator`
+    pub fn new() -> Self {
+        MarkdownDecorator
+    }
+}
+
+impl Default for MarkdownDecorator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decorator for MarkdownDecorator {
+    type RecordDecorator = MarkdownRecordDecorator;
+
+    fn decorate(&self, record: &Record) -> MarkdownRecordDecorator {
+        MarkdownRecordDecorator {
+            level: record.level(),
+            table: Cell::new(false),
+        }
+    }
+}
+
+impl DocumentDecorator for MarkdownDecorator {
+    fn write_heading(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        with_text(f, |text| {
+            io.write_all(b"### ")?;
+            write_text(io, text)?;
+            io.write_all(b"\n\n")
+        })
+    }
+}
+
+/// Decorator for a particular record
+pub struct MarkdownRecordDecorator {
+    level: Level,
+    /// Whether the key-value pairs of the current line are written as a table
+    table: Cell<bool>,
+}
+
+thread_local! {
+    /// Buffer text is formatted into before it is escaped
+    static TEXT_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// ASCII punctuation with a meaning in Markdown text
+const SPECIAL: &[u8] = b"\\`*_[]<>#|~&";
+
+/// Header of a key-value table, ending with the start of its first row
+const TABLE_START: &str = "\n\n| key | value |\n| --- | --- |\n| ";
+
+/// Badge in front of the level name
+fn level_badge(level: Level) -> &'static str {
+    match level {
+        Level::Critical => "\u{1f7e5}",
+        Level::Error => "\u{1f7e7}",
+        Level::Warning => "\u{1f7e8}",
+        Level::Info => "\u{1f7e9}",
+        Level::Debug => "\u{1f7e6}",
+        Level::Trace => "\u{2b1c}",
+    }
+}
+
+/// Run `f` with the text written by `text`, without trailing newlines
+fn with_text<F>(text: &dyn Fn(&mut dyn io::Write) -> io::Result<()>, f: F) -> io::Result<()>
+    where F: FnOnce(&str) -> io::Result<()>
+{
+    with_buffer(&TEXT_BUFFER, |buf| {
+        text(buf)?;
+        f(String::from_utf8_lossy(buf).trim_end_matches(&['\n', '\r'][..]))
+    })
+}
+
+/// Write text with special characters escaped, and line breaks as hard breaks
+///
+/// The lines after a break are escaped at their start too, where a list item or the
+/// underline of a heading would start a new block. Quotes and headings start with special
+/// characters, which are escaped anywhere.
+fn write_text(io: &mut dyn io::Write, text: &str) -> io::Result<()> {
+    for (i, line) in text.split('\n').enumerate() {
+        let mut line = line;
+        if i > 0 {
+            io.write_all(b"\\\n")?;
+            line = write_line_start(io, line)?;
+        }
+        write_inline(io, line)?;
+    }
+    Ok(())
+}
+
+/// Write the start of a line escaped if it is a block marker, returning the rest of the line
+fn write_line_start<'a>(io: &mut dyn io::Write, line: &'a str) -> io::Result<&'a str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let digits = line[indent..].bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match line.as_bytes().get(indent + digits) {
+        // Bullet list items and setext heading underlines
+        Some(b'-') | Some(b'+') | Some(b'=') if digits == 0 => indent,
+        // Ordered list items
+        Some(b'.') | Some(b')') if digits > 0 => indent + digits,
+        _ => return Ok(line),
+    };
+    io.write_all(&line.as_bytes()[..marker])?;
+    io.write_all(b"\\")?;
+    Ok(&line[marker..])
+}
+
+/// Write text on a single line with special characters escaped
+fn write_inline(io: &mut dyn io::Write, text: &str) -> io::Result<()> {
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte == b'\r' {
+            io.write_all(&bytes[start..i])?;
+            start = i + 1;
+        } else if SPECIAL.contains(&byte) {
+            io.write_all(&bytes[start..i])?;
+            io.write_all(b"\\")?;
+            start = i;
+        }
+    }
+    io.write_all(&bytes[start..])
+}
+
+/// Write text as a code span on a single line
+///
+/// Line breaks are written as escape sequences. Pipes are escaped in tables, where they
+/// would end the cell even inside a code span.
+fn write_code(io: &mut dyn io::Write, text: &str, table: bool) -> io::Result<()> {
+    let mut fence = 1;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        if run >= fence {
+            fence = run + 1;
+        }
+    }
+    let pad = text.is_empty() || text.starts_with('`') || text.ends_with('`') ||
+              (text.starts_with(' ') && text.ends_with(' '));
+
+    for _ in 0..fence {
+        io.write_all(b"`")?;
+    }
+    if pad {
+        io.write_all(b" ")?;
+    }
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escaped: &[u8] = match byte {
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'|' if table => b"\\|",
+            _ => continue,
+        };
+        io.write_all(&bytes[start..i])?;
+        io.write_all(escaped)?;
+        start = i + 1;
+    }
+    io.write_all(&bytes[start..])?;
+    if pad {
+        io.write_all(b" ")?;
+    }
+    for _ in 0..fence {
+        io.write_all(b"`")?;
+    }
+    Ok(())
+}
+
+impl MarkupDecorator for MarkdownRecordDecorator {
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        self.table.set(false);
+        f(io)?;
+        if self.table.get() {
+            io.write_all(b" |")?;
+        }
+        io.write_all(b"\n\n")
+    }
+
+    fn fmt_indent(&self, io: &mut dyn io::Write, indent: usize) -> io::Result<()> {
+        for _ in 0..indent {
+            io.write_all("\u{2003}\u{2003}".as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn fmt_pairs(&self,
+                 io: &mut dyn io::Write,
+                 after_msg: bool,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        if after_msg {
+            io.write_all(TABLE_START.as_bytes())?;
+            self.table.set(true);
+        }
+        f(io)
+    }
+
+    /// Pairs are in a table of their own, aligned already
+    fn fmt_padding(&self, io: &mut dyn io::Write, columns: usize) -> io::Result<()> {
+        let _ = (io, columns);
+        Ok(())
+    }
+
+    fn fmt_pair_separator(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        if self.table.get() {
+            io.write_all(b" |\n| ")
+        } else {
+            io.write_all(b", ")
+        }
+    }
+
+    fn fmt_key_separator(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        if self.table.get() {
+            io.write_all(b" | ")
+        } else {
+            io.write_all(b": ")
+        }
+    }
+}
+
+impl RecordDecorator for MarkdownRecordDecorator {
+    fn fmt_level(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        with_text(f, |text| {
+            write!(io, " {} ", level_badge(self.level))?;
+            write_code(io, text.trim(), false)?;
+            io.write_all(b" ")
+        })
+    }
+
+    fn fmt_msg(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        with_text(f, |text| {
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(());
+            }
+            io.write_all(b"**")?;
+            write_text(io, text)?;
+            io.write_all(b"**")
+        })
+    }
+
+    fn fmt_key(&self,
+               io: &mut dyn io::Write,
+               f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+               -> io::Result<()> {
+        with_text(f, |text| write_text(io, text))
+    }
+
+    fn fmt_separator(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        with_text(f, |text| write_text(io, text))
+    }
+
+    fn fmt_value(&self,
+                 io: &mut dyn io::Write,
+                 f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                 -> io::Result<()> {
+        with_text(f, |text| write_code(io, text, self.table.get()))
+    }
+
+    fn fmt_timestamp(&self,
+                     io: &mut dyn io::Write,
+                     f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                     -> io::Result<()> {
+        with_text(f, |text| write_text(io, text))
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use color_palette::ColorPalette;
+use decorator::{DOCUMENT_HEAD_END, DOCUMENT_HEAD_START, DocumentDecorator, FIELD_LEVEL,
+                HtmlDecorator, LANE_COLORS};
+use escape::Escape;
+use parse::{invalid, parse};
+use style::StyleTable;
+
+/// Abbreviated month names of the default timestamp format
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep",
+                            "Oct", "Nov", "Dec"];
+
+/// Merges html logs of several sources into a single timeline
+///
+/// Records are ordered by timestamp, keeping the order of each source for equal or
+/// unordered timestamps. Timestamps of the default format have no year, so a source
+/// starts a new year when its months wrap around. Every line is tagged with the label of
+/// its source, in a color per source. Context headers of compact mode stay with their
+/// records: when the source changes, the headers of the records that follow are printed
+/// again. With context lanes, headers are not repeated, and lanes are renumbered to keep
+/// the lanes of different sources apart.
+///
+/// Sources styled by classes, in Content Security Policy or fragment mode, get the rules
+/// of their `<style>` elements inlined again. Linked stylesheets are linked from the
+/// head of the merged document, so their classes should not overlap.
+///
+/// ```
+/// # extern crate slog_html;
+/// # fn main() {
+/// let api = "<pre style=\"margin-bottom:-0.5em\"><span data-field=\"ts\">Oct 19 04:37:51.497\
+///            </span><span data-field=\"level\"> INFO </span>request</pre>\n";
+/// let db = "<pre style=\"margin-bottom:-0.5em\"><span data-field=\"ts\">Oct 19 04:37:51.402\
+///           </span><span data-field=\"level\"> INFO </span>query</pre>\n";
+///
+/// let mut html = Vec::new();
+/// slog_html::Merger::new()
+///     .source("api", api)
+///     .source("db", db)
+///     .document(false)
+///     .merge(&mut html)
+///     .unwrap();
+///
+/// let html = String::from_utf8(html).unwrap();
+/// assert!(html.find("query").unwrap() < html.find("request").unwrap());
+/// # }
+/// ```
+pub struct Merger {
+    sources: Vec<(String, String)>,
+    document: bool,
+}
+
+impl Merger {
+    /// New `Merger` without sources
+    pub fn new() -> Self {
+        Merger {
+            sources: Vec::new(),
+            document: true,
+        }
+    }
+
+    /// Add the html log of a source, labeled `label`
+    pub fn source<L: Into<String>, H: Into<String>>(mut self, label: L, html: H) -> Self {
+        self.sources.push((label.into(), html.into()));
+        self
+    }
+
+    /// Write the document prologue and epilogue around the lines (default)
+    pub fn document(mut self, enabled: bool) -> Self {
+        self.document = enabled;
+        self
+    }
+
+    /// Write the records of all sources to `output`, ordered by timestamp
+    pub fn merge<W: io::Write>(&self, mut output: W) -> io::Result<()> {
+        let mut sources = Vec::with_capacity(self.sources.len());
+        let mut lanes = 0;
+        for (i, (label, html)) in self.sources.iter().enumerate() {
+            let color = LANE_COLORS[i % LANE_COLORS.len()];
+            sources.push(Source::read(label, color, html, &mut lanes)?);
+        }
+        number_years(&mut sources);
+
+        let decorator = HtmlDecorator::new(ColorPalette::default(), StyleTable::default());
+        if self.document {
+            output.write_all(DOCUMENT_HEAD_START.as_bytes())?;
+            let mut links: Vec<&str> = Vec::new();
+            for link in sources.iter().flat_map(|source| &source.links) {
+                if !links.contains(&link.as_str()) {
+                    links.push(link);
+                    writeln!(output, "{}", link)?;
+                }
+            }
+            output.write_all(DOCUMENT_HEAD_END.as_bytes())?;
+        }
+        let mut current = None;
+        loop {
+            let next = sources.iter()
+                .enumerate()
+                .filter_map(|(i, source)| source.blocks.get(source.next).map(|block| (i, block)))
+                .min_by(|&(_, a), &(_, b)| a.key.cmp(&b.key))
+                .map(|(i, _)| i);
+            let i = match next {
+                Some(i) => i,
+                None => break,
+            };
+            sources[i].write_block(&mut output, current != Some(i))?;
+            current = Some(i);
+        }
+        if self.document {
+            decorator.write_epilogue(&mut output)?;
+        }
+        output.flush()
+    }
+}
+
+impl Default for Merger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lines of a source, grouped into blocks of a record and the headers printed before it
+struct Source {
+    blocks: Vec<Block>,
+    /// Index of the next block to write
+    next: usize,
+    /// Context headers written so far, by depth
+    headers: Vec<String>,
+    /// Whether the lines are marked with context lanes
+    lanes: bool,
+    /// `<link>` elements of linked stylesheets
+    links: Vec<String>,
+}
+
+struct Block {
+    /// Sort key of the record's timestamp, after the year it is in
+    key: (i32, String),
+    /// Month of the record's timestamp, for timestamps without a year
+    month: Option<usize>,
+    /// Context headers with their depth
+    headers: Vec<(usize, String)>,
+    record: String,
+    depth: usize,
+}
+
+impl Source {
+    /// Split `html` into blocks, with every line tagged with `label`
+    ///
+    /// Lanes are renumbered from `lanes` on, which is advanced past the lanes of the source.
+    fn read(label: &str, color: &str, html: &str, lanes: &mut usize) -> io::Result<Self> {
+        let mut tag = Vec::new();
+        tag.write_all(b"<span data-source=\"")?;
+        Escape(&mut tag).write_all(label.as_bytes())?;
+        write!(tag, "\" style=\"color:#{};\">", color)?;
+        Escape(&mut tag).write_all(label.as_bytes())?;
+        tag.extend_from_slice(b"</span> ");
+        let tag = String::from_utf8_lossy(&tag);
+
+        let mut source = Source {
+            blocks: Vec::new(),
+            next: 0,
+            headers: Vec::new(),
+            lanes: false,
+            links: elements(html, "<link").filter(|link| link.contains("stylesheet")).collect(),
+        };
+        let rules = rules(html);
+        let mut lane_ids = HashMap::new();
+        let mut lane_id = |lane: &str| {
+            let next = *lanes + lane_ids.len();
+            let id = *lane_ids.entry(lane.to_owned()).or_insert(next);
+            id.to_string()
+        };
+        let mut headers = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find("<pre") {
+            rest = &rest[start..];
+            let end = rest.find("</pre>").ok_or_else(|| invalid("unterminated line"))?;
+            let end = end + "</pre>".len();
+            let (line, after) = rest.split_at(end);
+            rest = after.strip_prefix('\n').unwrap_or(after);
+
+            let open_end = line.find('>').ok_or_else(|| invalid("unterminated tag"))?;
+            let tagged = format!("{}{}{}\n", &line[..open_end + 1], tag, &line[open_end + 1..]);
+            let mut tagged = replace_attribute(&tagged, "data-lane", &mut lane_id);
+            tagged = replace_attribute(&tagged, "data-parent", &mut lane_id);
+            if !rules.is_empty() {
+                tagged = inline_classes(&tagged, &rules);
+            }
+            let depth = attribute(line, "data-depth").and_then(|depth| depth.parse().ok());
+            let depth = depth.unwrap_or(0);
+            source.lanes |= attribute(line, "data-lane").is_some();
+
+            if !line.contains(&format!("data-field=\"{}\"", FIELD_LEVEL)) {
+                headers.push((depth, tagged));
+                continue;
+            }
+            let records = parse(line)?;
+            let timestamp = records.first().map_or("", |record| record.timestamp.as_str());
+            let month = MONTHS.iter().position(|month| timestamp.starts_with(month));
+            source.blocks.push(Block {
+                key: (0, sort_key(timestamp, month)),
+                month,
+                headers: headers.split_off(0),
+                record: tagged,
+                depth,
+            });
+        }
+        *lanes += lane_ids.len();
+        Ok(source)
+    }
+
+    /// Write the next block, repeating its context headers first if `switched` sources
+    fn write_block(&mut self, io: &mut dyn io::Write, switched: bool) -> io::Result<()> {
+        let block = &self.blocks[self.next];
+        self.next += 1;
+
+        let printed = block.headers.first().map_or(block.depth, |&(depth, _)| depth);
+        self.headers.truncate(printed);
+        if switched && !self.lanes {
+            for header in &self.headers {
+                io.write_all(header.as_bytes())?;
+            }
+        }
+        for &(depth, ref header) in &block.headers {
+            io.write_all(header.as_bytes())?;
+            self.headers.truncate(depth);
+            self.headers.push(header.clone());
+        }
+        io.write_all(block.record.as_bytes())
+    }
+}
+
+/// Value of the first `name` attribute in `html`
+fn attribute<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let start = format!("{}=\"", name);
+    html.find(&start).and_then(|i| {
+        let value = &html[i + start.len()..];
+        value.find('"').map(|end| &value[..end])
+    })
+}
+
+/// Elements of `html` starting with `start`, up to the end of their opening tag
+fn elements<'a>(html: &'a str, start: &'a str) -> impl Iterator<Item = String> + 'a {
+    html.match_indices(start).filter_map(move |(i, _)| {
+        html[i..].find('>').map(|end| html[i..i + end + 1].to_owned())
+    })
+}
+
+/// Declarations of each class styled by the rules of the `<style>` elements in `html`
+///
+/// Rules are the ones of `write_stylesheet`, with a single class after an optional scope.
+fn rules(html: &str) -> HashMap<String, String> {
+    let mut rules = HashMap::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<style") {
+        rest = &rest[start..];
+        let end = rest.find("</style>").unwrap_or(rest.len());
+        let body = rest[..end].find('>').map_or("", |open_end| &rest[open_end + 1..end]);
+        for rule in body.lines() {
+            let (selector, css) = match rule.find('{') {
+                Some(i) if rule.ends_with('}') => (&rule[..i], &rule[i + 1..rule.len() - 1]),
+                _ => continue,
+            };
+            let class = selector.rsplit(' ').next().unwrap_or("");
+            if let Some(class) = class.strip_prefix('.') {
+                rules.insert(class.to_owned(), css.to_owned());
+            }
+        }
+        rest = &rest[end..];
+    }
+    rules
+}
+
+/// Replace the values of every `name` attribute in `html` by the result of `f`
+fn replace_attribute(html: &str, name: &str, f: &mut dyn FnMut(&str) -> String) -> String {
+    let start = format!(" {}=\"", name);
+    let mut replaced = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(&start) {
+        let value = &rest[i + start.len()..];
+        let end = match value.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+        replaced.push_str(&rest[..i + start.len()]);
+        replaced.push_str(&f(&value[..end]));
+        rest = &value[end..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// Replace `class` attributes in `html` by `style` attributes with the declarations of
+/// their classes in `rules`
+fn inline_classes(html: &str, rules: &HashMap<String, String>) -> String {
+    let with_markers = replace_attribute(html, "class", &mut |classes| {
+        let css: String = classes.split(' ')
+            .filter_map(|class| rules.get(class))
+            .map(|css| if css.ends_with(';') { css.clone() } else { format!("{};", css) })
+            .collect();
+        let mut escaped = Vec::new();
+        let _ = Escape(&mut escaped).write_all(css.as_bytes());
+        String::from_utf8_lossy(&escaped).into_owned()
+    });
+    with_markers.replace(" class=\"\"", "").replace(" class=\"", " style=\"")
+}
+
+/// Number the years of timestamps without a year, from the order of their months
+///
+/// A source starts a new year when its months go back by more than half a year. Its first
+/// month takes the year closest to the first month of all sources.
+fn number_years(sources: &mut [Source]) {
+    let first = sources.iter().flat_map(|source| &source.blocks).find_map(|block| block.month);
+    for source in sources {
+        let mut previous = first;
+        let mut year = 0;
+        for block in &mut source.blocks {
+            if let (Some(month), Some(previous)) = (block.month, previous) {
+                if month + 6 < previous {
+                    year += 1;
+                } else if previous + 6 < month {
+                    year -= 1;
+                }
+            }
+            previous = block.month.or(previous);
+            block.key.0 = year;
+        }
+    }
+}
+
+/// Key ordering timestamps of the default format by month, or else as text
+fn sort_key(timestamp: &str, month: Option<usize>) -> String {
+    match month {
+        Some(month) => format!("{:02}{}", month + 1, &timestamp[3..]),
+        None => timestamp.to_owned(),
+    }
+}
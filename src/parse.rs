@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::io;
+
+use slog::Level;
+
+use decorator::{FIELD_KEY, FIELD_LEVEL, FIELD_MESSAGE, FIELD_TIMESTAMP, FIELD_VALUE};
+
+/// Record read back from html written by `HtmlDecorator`
+///
+/// Values keep the text they were formatted to. In compact mode, the values of the
+/// context headers a record is nested under come first, like the logger values in full
+/// mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedRecord {
+    /// Timestamp, as formatted
+    pub timestamp: String,
+    /// Level
+    pub level: Level,
+    /// Message
+    pub msg: String,
+    /// Key-value pairs, in the order they were written
+    pub values: Vec<(String, String)>,
+}
+
+impl ParsedRecord {
+    /// Write the record as a line of JSON, with the keys of `slog-json`
+    pub fn write_json(&self, io: &mut dyn io::Write) -> io::Result<()> {
+        io.write_all(b"{\"ts\":")?;
+        write_json_string(io, &self.timestamp)?;
+        io.write_all(b",\"level\":")?;
+        write_json_string(io, self.level.as_short_str())?;
+        io.write_all(b",\"msg\":")?;
+        write_json_string(io, &self.msg)?;
+        for (key, value) in &self.values {
+            io.write_all(b",")?;
+            write_json_string(io, key)?;
+            io.write_all(b":")?;
+            write_json_string(io, value)?;
+        }
+        io.write_all(b"}\n")
+    }
+}
+
+/// Read back the records of html written by `HtmlDecorator`, in full or compact mode
+///
+/// Every line of output is a `<pre>` element, with its parts marked by `data-*`
+/// attributes. Collapsed and truncated text is read in full, unless the truncated part was
+/// dropped. Context headers left out by `ContextDiff::Omit` can not be restored.
+///
+/// ```
+/// # #[macro_use]
+/// # extern crate slog;
+/// # extern crate slog_html;
+/// # extern crate slog_stream;
+/// #
+/// # use slog::DrainExt;
+/// #
+/// # fn main() {
+/// # let output = slog_html::testing::SharedBuffer::new();
+/// # let buf = output.clone();
+/// let log = slog::Logger::root(slog_stream::stream(output, slog_html::default()).fuse(),
+///                              o!("version" => "1.0"));
+/// warn!(log, "disk almost full"; "free" => "2%");
+/// # let html = buf.contents();
+///
+/// let records = slog_html::parse(&html).unwrap();
+/// assert_eq!(records[0].msg, "disk almost full");
+/// assert_eq!(records[0].values[1], ("free".to_owned(), "2%".to_owned()));
+/// # }
+/// ```
+pub fn parse(html: &str) -> io::Result<Vec<ParsedRecord>> {
+    let mut parser = Parser {
+        records: Vec::new(),
+        contexts: HashMap::new(),
+        elements: Vec::new(),
+        line: None,
+    };
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        parser.text(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('>').ok_or_else(|| invalid("unterminated tag"))?;
+        parser.tag(&rest[1..end])?;
+        rest = &rest[end + 1..];
+    }
+    parser.text(rest);
+    Ok(parser.records)
+}
+
+/// Error for input that is not html written by this crate
+pub fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+/// Write `text` as a JSON string
+fn write_json_string(io: &mut dyn io::Write, text: &str) -> io::Result<()> {
+    io.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if c < ' ' => "",
+            _ => continue,
+        };
+        io.write_all(&text.as_bytes()[start..i])?;
+        if escaped.is_empty() {
+            write!(io, "\\u{:04x}", c as u32)?;
+        } else {
+            io.write_all(escaped.as_bytes())?;
+        }
+        start = i + c.len_utf8();
+    }
+    io.write_all(&text.as_bytes()[start..])?;
+    io.write_all(b"\"")
+}
+
+/// Decode the character references html escaping writes
+fn unescape(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let c = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "nbsp" => Some('\u{a0}'),
+            reference => {
+                let number = match reference.strip_prefix('#') {
+                    Some(hex) if hex.starts_with(['x', 'X']) => {
+                        u32::from_str_radix(&hex[1..], 16).ok()
+                    }
+                    Some(decimal) => decimal.parse().ok(),
+                    None => None,
+                };
+                number.and_then(::std::char::from_u32)
+            }
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Open element, with what it means for the text inside
+struct Element {
+    name: String,
+    /// Text inside is not part of a field, e.g. a `<summary>` marker
+    skip: bool,
+    field: Option<String>,
+}
+
+/// Parts of the line being read
+#[derive(Default)]
+struct Line {
+    depth: usize,
+    lane: usize,
+    /// Text of each field, in order
+    fields: Vec<(String, String)>,
+}
+
+struct Parser {
+    records: Vec<ParsedRecord>,
+    /// Pairs of the context headers at each depth, by lane
+    ///
+    /// Without lanes, all lines are in lane 0.
+    contexts: HashMap<usize, Vec<Vec<(String, String)>>>,
+    elements: Vec<Element>,
+    line: Option<Line>,
+}
+
+impl Parser {
+    fn text(&mut self, text: &str) {
+        if text.is_empty() || self.elements.iter().any(|element| element.skip) {
+            return;
+        }
+        let field = match self.elements.iter().rev().filter_map(|e| e.field.as_ref()).next() {
+            Some(field) => field,
+            None => return,
+        };
+        if let Some(ref mut line) = self.line {
+            let text = unescape(text);
+            match line.fields.last_mut() {
+                Some(&mut (ref last, ref mut value)) if last == field => value.push_str(&text),
+                _ => line.fields.push((field.clone(), text)),
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: &str) -> io::Result<()> {
+        if tag.starts_with('!') || tag.starts_with('?') {
+            return Ok(());
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(i) = self.elements.iter().rposition(|element| element.name == name) {
+                self.elements.truncate(i);
+            }
+            if name == "pre" {
+                self.end_line()?;
+            }
+            return Ok(());
+        }
+
+        let (name, attributes) = parse_tag(tag);
+        match name.as_str() {
+            "meta" | "br" | "link" | "img" | "hr" | "input" => return Ok(()),
+            "pre" => self.line = Some(Line::default()),
+            _ => {}
+        }
+        let attribute = |key: &str| {
+            attributes.iter().find(|&(k, _)| k == key).map(|(_, v)| v.as_str())
+        };
+
+        if let Some(ref mut line) = self.line {
+            if let Some(depth) = attribute("data-depth").and_then(|depth| depth.parse().ok()) {
+                line.depth = depth;
+            }
+            if let Some(lane) = attribute("data-lane").and_then(|lane| lane.parse().ok()) {
+                line.lane = lane;
+                // A new lane starts with the headers of the lane it branched off from
+                let parent = attribute("data-parent").and_then(|parent| parent.parse().ok());
+                if let (Some(parent), false) = (parent, self.contexts.contains_key(&lane)) {
+                    let context = self.contexts.get(&parent).cloned().unwrap_or_default();
+                    self.contexts.insert(lane, context);
+                }
+            }
+            if attribute("data-break").is_some() {
+                if let Some(&mut (_, ref mut text)) = line.fields.last_mut() {
+                    text.push('\n');
+                }
+            }
+        }
+        let skipped = self.elements.iter().any(|element| element.skip);
+        let field = attribute("data-field").map(str::to_owned);
+        let raw = attribute("data-raw");
+        // A field element starts a field, even if it has no text, unless it continues the
+        // field in a collapsed or truncated part
+        let collapsed = self.elements.iter().any(|element| element.name == "details");
+        if let (Some(field), false) = (field.as_ref(), skipped) {
+            if let Some(ref mut line) = self.line {
+                let continued = collapsed && raw.is_none() &&
+                                line.fields.last().is_some_and(|(last, _)| last == field);
+                if !continued {
+                    line.fields.push((field.clone(), raw.unwrap_or("").to_owned()));
+                }
+            }
+        }
+        let skip = name == "summary" || raw.is_some();
+        self.elements.push(Element {
+            name,
+            skip,
+            field,
+        });
+        Ok(())
+    }
+
+    /// Turn the line just read into a record, or a context header of the records after it
+    fn end_line(&mut self) -> io::Result<()> {
+        let line = match self.line.take() {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+
+        let mut timestamp = String::new();
+        let mut level = None;
+        let mut msg = String::new();
+        let mut values: Vec<(String, String)> = Vec::new();
+        for (field, text) in line.fields {
+            match field.as_str() {
+                FIELD_TIMESTAMP => timestamp = text,
+                FIELD_LEVEL => {
+                    let name = text.trim();
+                    level = Some(((1..)
+                        .map_while(Level::from_usize)
+                        .find(|level| level.as_short_str() == name)
+                        .ok_or_else(|| invalid(&format!("unknown level {}", name))))?);
+                }
+                FIELD_MESSAGE => msg = text,
+                FIELD_KEY => values.push((text, String::new())),
+                FIELD_VALUE => {
+                    if let Some(&mut (_, ref mut value)) = values.last_mut() {
+                        value.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let context = self.contexts.entry(line.lane).or_default();
+        let level = match level {
+            Some(level) => level,
+            None => {
+                // Context header
+                context.truncate(line.depth);
+                context.resize(line.depth, Vec::new());
+                context.push(values);
+                return Ok(());
+            }
+        };
+        let mut pairs: Vec<(String, String)> =
+            context.iter().take(line.depth).flat_map(|pairs| pairs.iter().cloned()).collect();
+        pairs.extend(values);
+        self.records.push(ParsedRecord {
+            timestamp,
+            level,
+            msg,
+            values: pairs,
+        });
+        Ok(())
+    }
+}
+
+/// Split a start tag into its lowercase name and its attributes, with values decoded
+fn parse_tag(tag: &str) -> (String, Vec<(String, String)>) {
+    let tag = tag.trim_end_matches('/');
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = tag[..name_end].to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (text, after) = match value.chars().next() {
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = after.trim_start();
+                unescape(text)
+            }
+            None => String::new(),
+        };
+        attributes.push((key, value));
+    }
+    (name, attributes)
+}
@@ -1,40 +1,100 @@
 use std::{io, fmt};
 
 use slog::ser;
-use slog_stream::RecordDecorator;
 
-pub struct Serializer<W, D: RecordDecorator> {
+use decorator::MarkupDecorator;
+use structured::StructuredValue;
+use width;
+
+macro_rules! s(
+    ($s:expr, $k:expr, $v:expr) => {
+        $s.emit_pair($k, &$v)?;
+    };
+);
+
+pub struct Serializer<'a, W, D: MarkupDecorator + 'a> {
     io: W,
-    decorator: D,
+    decorator: &'a D,
+    structured_values: bool,
+    /// Columns taken up by the last pair, if pairs are measured
+    width: Option<usize>,
 }
 
-impl<W: io::Write, D: RecordDecorator> Serializer<W, D> {
-    pub fn new(io: W, d: D) -> Self {
+impl<'a, W: io::Write, D: MarkupDecorator> Serializer<'a, W, D> {
+    pub fn new(io: W, d: &'a D, structured_values: bool) -> Self {
         Serializer {
-            io: io,
+            io,
             decorator: d,
+            structured_values,
+            width: None,
         }
     }
 
+    /// Measure the columns each pair takes up, as formatted by the decorator
+    pub fn measured(mut self) -> Self {
+        self.width = Some(0);
+        self
+    }
+
+    /// Columns taken up by the last pair, or 0 if pairs are not measured
+    pub fn width(&self) -> usize {
+        self.width.unwrap_or(0)
+    }
+
+    pub fn print_padding(&mut self, columns: usize) -> io::Result<()> {
+        self.decorator.fmt_padding(&mut self.io, columns)
+    }
+
     pub fn print_comma(&mut self) -> io::Result<()> {
-        try!(self.decorator.fmt_separator(&mut self.io, &|io: &mut io::Write| write!(io, ", ")));
-        Ok(())
+        self.decorator.fmt_pair_separator(&mut self.io)
     }
 
-    pub fn finish(self) -> (W, D) {
-        (self.io, self.decorator)
+    pub fn finish(self) -> W {
+        self.io
     }
-}
 
-macro_rules! s(
-    ($s:expr, $k:expr, $v:expr) => {
-        try!($s.decorator.fmt_key(&mut $s.io, &|io: &mut io::Write| write!(io, "{}", $k)));
-        try!($s.decorator.fmt_separator(&mut $s.io, &|io: &mut io::Write| write!(io, ": ")));
-        try!($s.decorator.fmt_value(&mut $s.io, &|io: &mut io::Write| write!(io, "{}", $v)));
-    };
-);
+    /// Emit a key and a plain value
+    fn emit_pair(&mut self, key: &str, val: &dyn fmt::Display) -> io::Result<()> {
+        self.decorator.fmt_key(&mut self.io, &|io: &mut dyn io::Write| write!(io, "{}", key))?;
+        self.decorator.fmt_key_separator(&mut self.io)?;
+        if self.width.is_none() {
+            return self.decorator
+                .fmt_value(&mut self.io, &|io: &mut dyn io::Write| write!(io, "{}", val));
+        }
+        // Measured values are formatted only once
+        let val = val.to_string();
+        self.measure(key, &val);
+        self.decorator
+            .fmt_value(&mut self.io, &|io: &mut dyn io::Write| io.write_all(val.as_bytes()))
+    }
+
+    /// Set the width of a pair, counting the key separator
+    fn measure(&mut self, key: &str, val: &str) {
+        if self.width.is_some() {
+            self.width = Some(width::text_width(key) + 2 + self.decorator.value_width(val));
+        }
+    }
+
+    /// Emit text, rendering it as a structured value if it is one
+    fn emit_text(&mut self, key: &str, val: &str) -> ser::Result {
+        let structured = if self.structured_values {
+            StructuredValue::detect(val)
+        } else {
+            None
+        };
+        if let Some(structured) = structured {
+            self.decorator.fmt_key(&mut self.io, &|io: &mut dyn io::Write| write!(io, "{}", key))?;
+            self.decorator.fmt_key_separator(&mut self.io)?;
+            self.decorator.fmt_structured_value(&mut self.io, &structured)?;
+            self.measure(key, &structured.summary());
+        } else {
+            s!(self, key, val);
+        }
+        Ok(())
+    }
+}
 
-impl<W: io::Write, D: RecordDecorator> ser::Serializer for Serializer<W, D> {
+impl<'a, W: io::Write, D: MarkupDecorator> ser::Serializer for Serializer<'a, W, D> {
     fn emit_none(&mut self, key: &str) -> ser::Result {
         s!(self, key, "None");
         Ok(())
@@ -116,12 +176,15 @@ impl<W: io::Write, D: RecordDecorator> ser::Serializer for Serializer<W, D> {
     }
 
     fn emit_str(&mut self, key: &str, val: &str) -> ser::Result {
-        s!(self, key, val);
-        Ok(())
+        self.emit_text(key, val)
     }
 
     fn emit_arguments(&mut self, key: &str, val: &fmt::Arguments) -> ser::Result {
-        s!(self, key, val);
-        Ok(())
+        if self.structured_values {
+            self.emit_text(key, &fmt::format(*val))
+        } else {
+            s!(self, key, val);
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,468 @@
+use std::fmt;
+
+/// Maximum nesting depth accepted when parsing JSON values
+const MAX_DEPTH: usize = 64;
+
+/// Value with an internal structure, detected in serialized text
+pub enum StructuredValue {
+    /// Tree parsed from JSON text
+    Tree(TreeNode),
+    /// Pre-formatted multi-line text, like the pretty `Debug` output (`{:#?}`)
+    Block(String),
+}
+
+impl StructuredValue {
+    /// Detect a structured value in `text`
+    ///
+    /// Returns `None` for plain text, which is rendered as is.
+    pub fn detect(text: &str) -> Option<StructuredValue> {
+        let trimmed = text.trim();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            if let Some(node) = TreeNode::parse_json(trimmed) {
+                return Some(StructuredValue::Tree(node));
+            }
+        }
+        if is_debug_pretty(trimmed) {
+            return Some(StructuredValue::Block(trimmed.to_owned()));
+        }
+        None
+    }
+
+    /// One-line summary of the value
+    pub fn summary(&self) -> String {
+        match *self {
+            StructuredValue::Tree(ref node) => node.to_string(),
+            StructuredValue::Block(ref text) => summarize_block(text),
+        }
+    }
+}
+
+/// Node of a JSON tree
+pub enum TreeNode {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// Number, kept in its original notation
+    Number(String),
+    /// String (unescaped)
+    String(String),
+    /// Array of nodes
+    Array(Vec<TreeNode>),
+    /// Object with its members in their original order
+    Object(Vec<(String, TreeNode)>),
+}
+
+impl TreeNode {
+    /// Parse a JSON object or array
+    ///
+    /// Returns `None` if `text` is not valid JSON, or if its top-level value is a scalar.
+    pub fn parse_json(text: &str) -> Option<TreeNode> {
+        let mut parser = JsonParser {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+        let node = match parser.parse_value(0) {
+            Some(node @ TreeNode::Array(_)) |
+            Some(node @ TreeNode::Object(_)) => node,
+            _ => return None,
+        };
+        parser.skip_whitespace();
+        if parser.pos == parser.bytes.len() {
+            Some(node)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the node is an array or object
+    pub fn is_container(&self) -> bool {
+        matches!(*self, TreeNode::Array(_) | TreeNode::Object(_))
+    }
+
+    /// Short description of a container, like `{3 keys}` or `[1 item]`
+    pub fn outline(&self) -> String {
+        fn plural(n: usize, word: &str) -> String {
+            if n == 1 {
+                format!("{} {}", n, word)
+            } else {
+                format!("{} {}s", n, word)
+            }
+        }
+        match *self {
+            TreeNode::Array(ref items) => format!("[{}]", plural(items.len(), "item")),
+            TreeNode::Object(ref members) => format!("{{{}}}", plural(members.len(), "key")),
+            ref scalar => scalar.to_string(),
+        }
+    }
+}
+
+/// Compact JSON representation
+impl fmt::Display for TreeNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TreeNode::Null => write!(f, "null"),
+            TreeNode::Bool(b) => write!(f, "{}", b),
+            TreeNode::Number(ref n) => write!(f, "{}", n),
+            TreeNode::String(ref s) => write!(f, "{:?}", s),
+            TreeNode::Array(ref items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            TreeNode::Object(ref members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{:?}:{}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Whether `text` looks like the output of `{:#?}`
+///
+/// That is, more than one line, opening a bracket at the end of the first line and
+/// closing it on the last line.
+fn is_debug_pretty(text: &str) -> bool {
+    let first = match text.lines().next() {
+        Some(line) => line.trim_end(),
+        None => return false,
+    };
+    let last = match text.lines().last() {
+        Some(line) => line.trim(),
+        None => return false,
+    };
+    let closing = match first.chars().last() {
+        Some('{') => '}',
+        Some('(') => ')',
+        Some('[') => ']',
+        _ => return false,
+    };
+    text.contains('\n') && last.starts_with(closing)
+}
+
+/// Join the lines of a pretty-printed block into a single line
+///
+/// Spacing follows the compact `{:?}` output: braces are padded, brackets and parentheses
+/// are not.
+fn summarize_block(text: &str) -> String {
+    let mut summary = String::with_capacity(text.len());
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let closes_tight = line.starts_with(')') || line.starts_with(']');
+        if (closes_tight || line.starts_with('}')) && summary.ends_with(',') {
+            summary.pop();
+        }
+        let opened_tight = summary.ends_with('(') || summary.ends_with('[');
+        if !summary.is_empty() && !opened_tight && !closes_tight {
+            summary.push(' ');
+        }
+        summary.push_str(line);
+    }
+    summary
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_literal(&mut self, literal: &[u8]) -> bool {
+        if self.bytes[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Option<TreeNode> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'"') => self.parse_string().map(TreeNode::String),
+            Some(b't') if self.eat_literal(b"true") => Some(TreeNode::Bool(true)),
+            Some(b'f') if self.eat_literal(b"false") => Some(TreeNode::Bool(false)),
+            Some(b'n') if self.eat_literal(b"null") => Some(TreeNode::Null),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            _ => None,
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Option<TreeNode> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        if self.eat(b'}') {
+            return Some(TreeNode::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            if !self.eat(b':') {
+                return None;
+            }
+            let value = self.parse_value(depth + 1)?;
+            members.push((key, value));
+            if self.eat(b'}') {
+                return Some(TreeNode::Object(members));
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Option<TreeNode> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        if self.eat(b']') {
+            return Some(TreeNode::Array(items));
+        }
+        loop {
+            items.push(self.parse_value(depth + 1)?);
+            if self.eat(b']') {
+                return Some(TreeNode::Array(items));
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<TreeNode> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let leading_zero = self.peek() == Some(b'0');
+        let digits = self.skip_digits();
+        if digits == 0 || leading_zero && digits > 1 {
+            return None;
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if self.skip_digits() == 0 {
+                return None;
+            }
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                return None;
+            }
+        }
+        let number = String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned();
+        Some(TreeNode::Number(number))
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        if self.peek() != Some(b'"') {
+            return None;
+        }
+        self.pos += 1;
+        let mut buf: Vec<u8> = Vec::new();
+        loop {
+            match self.peek() {
+                None => return None,
+                Some(b'"') => {
+                    self.pos += 1;
+                    return String::from_utf8(buf).ok();
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.parse_unicode_escape()?,
+                        _ => return None,
+                    };
+                    self.pos += 1;
+                    let mut utf8 = [0; 4];
+                    buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                Some(byte) if byte < 0x20 => return None,
+                Some(byte) => {
+                    buf.push(byte);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Parse the `XXXX` of `\uXXXX`, including a trailing low surrogate escape
+    ///
+    /// Leaves `pos` on the last hex digit.
+    fn parse_unicode_escape(&mut self) -> Option<char> {
+        let high = self.parse_hex4()?;
+        if !(0xd800..=0xdbff).contains(&high) {
+            return ::std::char::from_u32(high);
+        }
+        if !self.bytes[self.pos + 1..].starts_with(b"\\u") {
+            return None;
+        }
+        self.pos += 2;
+        let low = self.parse_hex4()?;
+        if !(0xdc00..=0xdfff).contains(&low) {
+            return None;
+        }
+        ::std::char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+    }
+
+    /// Parse four hex digits following `pos`, leaving `pos` on the last one
+    fn parse_hex4(&mut self) -> Option<u32> {
+        let digits = self.bytes.get(self.pos + 1..self.pos + 5)?;
+        // `from_str_radix` would take a sign too
+        if !digits.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        let digits = ::std::str::from_utf8(digits).ok()?;
+        let value = u32::from_str_radix(digits, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<String> {
+        TreeNode::parse_json(text).map(|node| node.to_string())
+    }
+
+    #[test]
+    fn nested_json_keeps_member_order() {
+        let text = r#" { "b": [1, -2.5e3, {"c": null}], "a": {"d": [true, false, []]} } "#;
+        assert_eq!(parse(text).unwrap(),
+                   r#"{"b":[1,-2.5e3,{"c":null}],"a":{"d":[true,false,[]]}}"#);
+        let node = TreeNode::parse_json(text).unwrap();
+        assert_eq!(node.outline(), "{2 keys}");
+        match node {
+            TreeNode::Object(ref members) => assert_eq!(members[0].1.outline(), "[3 items]"),
+            _ => panic!("not an object"),
+        }
+    }
+
+    #[test]
+    fn escapes_are_unescaped() {
+        let node = TreeNode::parse_json(r#"["a\"b\\c\/\n\t", "é🚀"]"#).unwrap();
+        match node {
+            TreeNode::Array(ref items) => {
+                match (&items[0], &items[1]) {
+                    (TreeNode::String(a), TreeNode::String(b)) => {
+                        assert_eq!(a, "a\"b\\c/\n\t");
+                        assert_eq!(b, "\u{e9}\u{1f680}");
+                    }
+                    _ => panic!("not strings"),
+                }
+            }
+            _ => panic!("not an array"),
+        }
+    }
+
+    #[test]
+    fn zeros_and_hex_escapes_are_accepted() {
+        assert_eq!(parse("[0, -0, 0.5, -0.05e2, 10]").unwrap(), "[0,-0,0.5,-0.05e2,10]");
+        assert!(parse(r#"["\u0041\u00e9\uD83D\uDE80"]"#).is_some());
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        for text in &[r#"{"a" 1}"#,
+                      r#"{"a": 1,}"#,
+                      r#"[1 2]"#,
+                      r#"{a: 1}"#,
+                      r#"[01.]"#,
+                      r#"[01]"#,
+                      r#"[-00.5]"#,
+                      r#"[1e]"#,
+                      r#"["tab	inside"]"#,
+                      r#"["\x"]"#,
+                      r#"["\ud83d"]"#,
+                      r#"["\udc00"]"#,
+                      r#"["\u+041"]"#,
+                      r#"["\u-041"]"#,
+                      r#"[nul]"#,
+                      r#"[1] trailing"#,
+                      "\"top-level string\"",
+                      "42"] {
+            assert!(parse(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn truncated_json_is_rejected() {
+        let text = r#"{"a": [1, {"b": "cA"}], "d": true}"#;
+        assert!(parse(text).is_some());
+        for end in 0..text.len() {
+            assert!(parse(&text[..end]).is_none(), "{}", &text[..end]);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_some());
+        assert!(parse(&nested(MAX_DEPTH + 2)).is_none());
+    }
+
+    #[test]
+    fn detects_pretty_debug_output() {
+        let text = "Point {\n    x: 1,\n    y: [\n        2,\n    ],\n}";
+        let value = StructuredValue::detect(text).unwrap();
+        assert_eq!(value.summary(), "Point { x: 1, y: [2] }");
+        assert!(StructuredValue::detect("plain {text}").is_none());
+        assert!(StructuredValue::detect("{not json").is_none());
+    }
+}
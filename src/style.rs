@@ -1,7 +1,15 @@
-#[derive(Clone, Copy)]
+use color_palette::ColorPalette;
+use contrast;
+use contrast::ContrastWarning;
+use css;
+use css::CssError;
+use decorator::{GUIDE_COLOR, LANE_COLORS};
+use slog::Level;
+
+#[derive(Clone, Copy, Default)]
 /// Formatting style
 pub struct Style {
-    /// Optionally use custom color (hexadecimal color code)
+    /// Optionally use custom color (hexadecimal color code of 3 or 6 digits, without `#`)
     pub color: Option<&'static str>,
     /// Use bold font
     pub bold: bool,
@@ -9,21 +17,13 @@ pub struct Style {
     pub italic: bool,
     /// Use custom CSS style
     ///
+    /// Declarations may only set properties for colors, fonts, text decoration and spacing,
+    /// with plain values. `FormatBuilder::build` fails on anything else.
+    ///
     /// Example: Some("background-color: #ffff7f;")
     pub custom: Option<&'static str>,
 }
 
-impl Default for Style {
-    fn default() -> Self {
-        Style {
-            color: None,
-            bold: false,
-            italic: false,
-            custom: None,
-        }
-    }
-}
-
 #[derive(Clone, Copy)]
 pub struct StyleTable {
     pub level: Style,
@@ -32,6 +32,81 @@ pub struct StyleTable {
     pub key: Style,
     pub value: Style,
     pub separator: Style,
+    pub duration: Style,
+    pub gap: Style,
+    /// Background to raise the contrast of colors against, if they are adjusted
+    pub adjust_contrast: Option<&'static str>,
+}
+
+impl StyleTable {
+    /// Check the colors and custom CSS of every style, the level colors of `color_palette`
+    /// and `background`
+    ///
+    /// Colors must be hexadecimal color codes, see `css::check_color`.
+    pub fn validate(&self, color_palette: &ColorPalette, background: &str) -> Result<(), CssError> {
+        let styles = [&self.level,
+                      &self.timestamp,
+                      &self.message,
+                      &self.key,
+                      &self.value,
+                      &self.separator,
+                      &self.duration,
+                      &self.gap];
+        for style in &styles {
+            if let Some(color) = style.color {
+                css::check_color(color)?;
+            }
+            if let Some(custom) = style.custom {
+                css::parse(custom)?;
+            }
+        }
+        for level in (1..).map_while(Level::from_usize) {
+            css::check_color(color_palette.level_to_color(level))?;
+        }
+        if let Some(background) = self.adjust_contrast {
+            css::check_color(background)?;
+        }
+        css::check_color(background)
+    }
+
+    /// Check the contrast of every color against `background`
+    pub fn check_contrast(&self,
+                          color_palette: &ColorPalette,
+                          background: &str)
+                          -> Vec<ContrastWarning> {
+        let mut colors = Vec::new();
+        for level in (1..).map_while(Level::from_usize) {
+            let slot = match level {
+                Level::Critical => "critical level",
+                Level::Error => "error level",
+                Level::Warning => "warning level",
+                Level::Info => "info level",
+                Level::Debug => "debug level",
+                Level::Trace => "trace level",
+            };
+            colors.push((slot, self.level.color.unwrap_or(color_palette.level_to_color(level))));
+        }
+        let styles = [("timestamp", &self.timestamp),
+                      ("message", &self.message),
+                      ("key", &self.key),
+                      ("value", &self.value),
+                      ("separator", &self.separator),
+                      ("duration", &self.duration),
+                      ("gap", &self.gap)];
+        for &(slot, style) in &styles {
+            if let Some(color) = style.color {
+                colors.push((slot, color));
+            }
+        }
+        // Labels of merged sources take the colors of lanes
+        for &color in &LANE_COLORS {
+            colors.push(("context lane or merge source", color));
+        }
+        colors.push(("tree guide", GUIDE_COLOR));
+        colors.into_iter()
+            .filter_map(|(slot, color)| contrast::check(slot, color, background))
+            .collect()
+    }
 }
 
 impl Default for StyleTable {
@@ -73,6 +148,19 @@ impl Default for StyleTable {
                 italic: false,
                 custom: None,
             },
+            duration: Style {
+                color: Some("707070"),
+                bold: false,
+                italic: false,
+                custom: None,
+            },
+            gap: Style {
+                color: Some("c84400"),
+                bold: true,
+                italic: false,
+                custom: None,
+            },
+            adjust_contrast: None,
         }
     }
 }
@@ -0,0 +1,298 @@
+//! Helpers for testing html log output
+//!
+//! Only compiled with the `testing` feature, usually enabled for tests only:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! slog-html = { version = "0.1", features = ["testing"] }
+//! ```
+//!
+//! `TestLog` formats records into memory, with a timestamp counting the records instead of
+//! the time, so that output is the same on every run. Its output can be checked record by
+//! record, or compared as a whole to a snapshot file.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate slog;
+//! # extern crate slog_html;
+//! #
+//! # fn main() {
+//! let log = slog_html::testing::TestLog::new(slog_html::new().compact());
+//! let request = log.logger().new(o!("request_id" => 42));
+//! warn!(request, "slow response"; "ms" => 1200);
+//!
+//! log.assert_message(slog::Level::Warning, "slow response");
+//! log.assert_record(slog::Level::Warning, "request_id", "42");
+//! log.assert_record(slog::Level::Warning, "ms", "1200");
+//! # }
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use slog::{DrainExt, Level, Logger};
+use slog_stream;
+
+use FormatBuilder;
+use decorator::FIELD_DURATION;
+use parse::{ParsedRecord, parse};
+
+/// Environment variable that makes `assert_snapshot` overwrite snapshots that differ
+pub const UPDATE_SNAPSHOTS: &str = "SLOG_HTML_UPDATE_SNAPSHOTS";
+
+/// Lines of context around each change in a snapshot diff
+const DIFF_CONTEXT: usize = 2;
+
+/// Logger writing html into memory, with deterministic timestamps
+///
+/// The timestamp of the n-th record is n, zero-padded to four digits.
+pub struct TestLog {
+    logger: Logger,
+    output: SharedBuffer,
+}
+
+/// Writer appending to a buffer in memory, shared by its clones
+///
+/// Give a clone to a drain and read what it wrote from the original.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// New empty `SharedBuffer`
+    pub fn new() -> Self {
+        SharedBuffer::default()
+    }
+
+    /// Text written so far, with invalid UTF-8 replaced
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().expect("failed to lock shared buffer")).into_owned()
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("failed to lock shared buffer").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TestLog {
+    /// New `TestLog` formatting records with `builder`, replacing its timestamp function
+    ///
+    /// Panics if the custom CSS of a style is invalid.
+    pub fn new(builder: FormatBuilder) -> Self {
+        let counter = AtomicUsize::new(0);
+        let format = builder.use_custom_timestamp(move |io| {
+                write!(io, "{:04}", counter.fetch_add(1, Ordering::SeqCst) + 1)
+            })
+            .build()
+            .unwrap_or_else(|e| panic!("{}", e));
+        let output = SharedBuffer::new();
+        let drain = slog_stream::stream(output.clone(), format).fuse();
+        TestLog {
+            logger: Logger::root(drain, None),
+            output,
+        }
+    }
+
+    /// Root logger writing to this log
+    pub fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    /// Output written so far, normalized
+    pub fn html(&self) -> String {
+        normalize(&self.output.contents())
+    }
+
+    /// Output written so far, as is, with the times that `html` normalizes
+    pub fn raw_html(&self) -> String {
+        self.output.contents()
+    }
+
+    /// Records written so far, read back with `parse`
+    pub fn records(&self) -> Vec<ParsedRecord> {
+        parse(&self.html()).expect("failed to parse test output")
+    }
+
+    /// Assert that a record at `level` has the message `msg`
+    pub fn assert_message(&self, level: Level, msg: &str) {
+        let records = self.records();
+        if !records.iter().any(|record| record.level == level && record.msg == msg) {
+            panic!("no {} record with message {:?} in:\n{}",
+                   level.as_str(),
+                   msg,
+                   describe(&records));
+        }
+    }
+
+    /// Assert that a record at `level` has `key` with the formatted value `value`
+    ///
+    /// In compact mode, the values of the context headers count as values of the records
+    /// nested under them.
+    pub fn assert_record(&self, level: Level, key: &str, value: &str) {
+        let records = self.records();
+        let found = records.iter().any(|record| {
+            record.level == level && record.values.iter().any(|(k, v)| k == key && v == value)
+        });
+        if !found {
+            panic!("no {} record with {} = {:?} in:\n{}",
+                   level.as_str(),
+                   key,
+                   value,
+                   describe(&records));
+        }
+    }
+
+    /// Assert that the output equals the snapshot at `path`, see `assert_snapshot`
+    pub fn assert_snapshot<P: AsRef<Path>>(&self, path: P) {
+        assert_snapshot(path, &self.html());
+    }
+}
+
+/// Make output comparable across runs and platforms
+///
+/// Elapsed and delta times are replaced by `*`, dropping the style that tells gaps apart,
+/// and line endings become `\n`.
+pub fn normalize(html: &str) -> String {
+    let html = html.replace("\r\n", "\n");
+    let start_tag = format!("<span data-field=\"{}\"", FIELD_DURATION);
+    let mut normalized = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find(&start_tag) {
+        normalized.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.find("</span>") {
+            Some(end) => {
+                normalized.push_str(&start_tag);
+                normalized.push_str(">*</span>");
+                rest = &rest[end + "</span>".len()..];
+            }
+            None => break,
+        }
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// Assert that `actual` equals the contents of the snapshot file at `path`
+///
+/// If the environment variable `SLOG_HTML_UPDATE_SNAPSHOTS` is set, snapshots that are
+/// missing or differ are written instead of failing, to be reviewed and committed like any
+/// other test file. On failure, the panic message shows the changed lines.
+///
+/// Relative paths are resolved against the working directory, the package root when run
+/// by `cargo test`.
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, actual: &str) {
+    let path = path.as_ref();
+    let update = ::std::env::var_os(UPDATE_SNAPSHOTS).is_some();
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected.replace("\r\n", "\n"),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound && update => {
+            write_snapshot(path, actual);
+            return;
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            panic!("snapshot {} is missing (set {} to write it)",
+                   path.display(),
+                   UPDATE_SNAPSHOTS)
+        }
+        Err(e) => panic!("failed to read snapshot {}: {}", path.display(), e),
+    };
+    if expected == actual {
+        return;
+    }
+    if update {
+        write_snapshot(path, actual);
+        return;
+    }
+    panic!("output differs from snapshot {} (set {} to update it):\n{}",
+           path.display(),
+           UPDATE_SNAPSHOTS,
+           diff(&expected, actual));
+}
+
+fn write_snapshot(path: &Path, contents: &str) {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|e| panic!("failed to create {}: {}", dir.display(), e));
+    }
+    fs::write(path, contents)
+        .unwrap_or_else(|e| panic!("failed to write snapshot {}: {}", path.display(), e));
+}
+
+/// List records for a failed assertion
+fn describe(records: &[ParsedRecord]) -> String {
+    if records.is_empty() {
+        return "  (no records)\n".to_owned();
+    }
+    let mut description = String::new();
+    for record in records {
+        description.push_str(&format!("  {} {} {:?}",
+                                      record.timestamp,
+                                      record.level.as_str(),
+                                      record.msg));
+        for (key, value) in &record.values {
+            description.push_str(&format!(", {} = {:?}", key, value));
+        }
+        description.push('\n');
+    }
+    description
+}
+
+/// Line diff of `expected` and `actual`, with removed lines as `-` and added ones as `+`
+fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Length of the longest common subsequence of the remaining lines
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', i + 1, old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            lines.push(('+', j + 1, new[j]));
+            j += 1;
+        } else {
+            lines.push(('-', i + 1, old[i]));
+            i += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut output = String::new();
+    let mut last = None;
+    for (k, &(sign, number, line)) in lines.iter().enumerate() {
+        let near = changed.iter().any(|&c| c + DIFF_CONTEXT >= k && k + DIFF_CONTEXT >= c);
+        if !near {
+            continue;
+        }
+        if last.is_some_and(|last| last + 1 < k) {
+            output.push_str("  ...\n");
+        }
+        output.push_str(&format!("{} {:>4} | {}\n", sign, number, line));
+        last = Some(k);
+    }
+    output
+}
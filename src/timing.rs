@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use slog::OwnedKeyValueList;
+
+/// Number of logger contexts after which their last record times are forgotten
+const MAX_CONTEXTS: usize = 1024;
+
+/// Time columns shown for each record
+pub struct Timing {
+    start: Instant,
+    elapsed: bool,
+    delta: bool,
+    context_delta: bool,
+    gap_threshold: Option<Duration>,
+    last: Mutex<Option<Instant>>,
+    last_per_context: Mutex<HashMap<usize, ContextTime>>,
+}
+
+/// Time of the last record of a logger context
+struct ContextTime {
+    /// Keeps the node alive, so that its id is not reused by another node
+    _node: OwnedKeyValueList,
+    last: Instant,
+}
+
+/// Time columns of a record
+pub struct RecordTiming {
+    /// Time since the drain started
+    pub elapsed: Option<Duration>,
+    /// Time since the previous record, `Some(None)` for the first one
+    pub delta: Option<Option<Duration>>,
+    /// Time since the previous record of the same logger context
+    pub context_delta: Option<Option<Duration>>,
+}
+
+impl Timing {
+    pub fn new(elapsed: bool,
+               delta: bool,
+               context_delta: bool,
+               gap_threshold: Option<Duration>)
+               -> Self {
+        Timing {
+            start: Instant::now(),
+            elapsed,
+            delta,
+            context_delta,
+            gap_threshold,
+            last: Mutex::new(None),
+            last_per_context: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take the time of a record logged with `logger_values`
+    pub fn record(&self, logger_values: &OwnedKeyValueList) -> RecordTiming {
+        let now = Instant::now();
+        let elapsed = if self.elapsed {
+            Some(now.duration_since(self.start))
+        } else {
+            None
+        };
+        let delta = if self.delta {
+            let mut last = self.last.lock().expect("failed to lock last record time");
+            let delta = last.map(|last| now.duration_since(last));
+            *last = Some(now);
+            Some(delta)
+        } else {
+            None
+        };
+        let context_delta = if self.context_delta {
+            let mut last_per_context = self.last_per_context
+                .lock()
+                .expect("failed to lock last record times");
+            if last_per_context.len() >= MAX_CONTEXTS {
+                last_per_context.clear();
+            }
+            // The node is kept in the map, so its id cannot be taken by a new node
+            #[allow(deprecated)]
+            let id = logger_values.id();
+            let context = ContextTime {
+                _node: logger_values.clone(),
+                last: now,
+            };
+            let last = last_per_context.insert(id, context);
+            Some(last.map(|context| now.duration_since(context.last)))
+        } else {
+            None
+        };
+        RecordTiming {
+            elapsed,
+            delta,
+            context_delta,
+        }
+    }
+
+    /// Whether the time between two records is long enough to be highlighted
+    pub fn is_gap(&self, delta: Duration) -> bool {
+        self.gap_threshold.is_some_and(|threshold| delta > threshold)
+    }
+}
+
+/// Format a duration for a time column
+///
+/// The unit is chosen to keep three significant digits or more.
+pub fn fmt_duration(duration: Duration) -> String {
+    let nanos = duration.as_secs() as f64 * 1e9 + duration.subsec_nanos() as f64;
+    if nanos < 1e3 {
+        format!("{}ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.1}\u{b5}s", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.1}ms", nanos / 1e6)
+    } else {
+        format!("{:.3}s", nanos / 1e9)
+    }
+}
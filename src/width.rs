@@ -0,0 +1,9 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `text` takes up in a monospace font, for its widest line
+///
+/// Wide characters, like CJK ideographs and most emoji, take up two columns, and combining
+/// marks none.
+pub fn text_width(text: &str) -> usize {
+    text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0)
+}
@@ -0,0 +1,110 @@
+//! Output readable with assistive tech and without telling colors apart
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::testing::TestLog;
+use slog_html::{ColorPalette, MIN_CONTRAST, Style};
+
+fn log_levels(log: &TestLog) {
+    crit!(log.logger(), "disk full");
+    error!(log.logger(), "write failed");
+    warn!(log.logger(), "retrying");
+    info!(log.logger(), "written"; "bytes" => 512);
+}
+
+#[test]
+fn documents_have_a_log_container() {
+    let format = slog_html::new().build().unwrap();
+    let mut document = Vec::new();
+    format.write_prologue(&mut document).unwrap();
+    format.write_epilogue(&mut document).unwrap();
+    let document = String::from_utf8(document).unwrap();
+    assert!(document.contains("<body>\n<div role=\"log\">\n</div>\n</body>"),
+            "{}",
+            document);
+
+    let fragment = slog_html::new().fragment("log").build().unwrap();
+    let mut prologue = Vec::new();
+    fragment.write_prologue(&mut prologue).unwrap();
+    let prologue = String::from_utf8(prologue).unwrap();
+    assert!(prologue.starts_with("<div class=\"log\" role=\"log\">"), "{}", prologue);
+}
+
+#[test]
+fn levels_are_spoken_in_full() {
+    let log = TestLog::new(slog_html::new());
+    log_levels(&log);
+    let html = log.html();
+    for &(spoken, short) in &[("critical", "CRIT"), ("error", "ERRO"), ("warn", "WARN"),
+                              ("info", "INFO")] {
+        let level = format!("role=\"img\" aria-label=\"{}\"> {} </span>", spoken, short);
+        assert!(html.contains(&level), "{}", html);
+    }
+    // The spoken name is not part of the text, which is copied once
+    assert!(!html.contains(">warn "), "{}", html);
+    assert_eq!(log.records().len(), 4);
+    log.assert_message(slog::Level::Warning, "retrying");
+
+    let log = TestLog::new(slog_html::new().csp(slog_html::Csp::Nonce("bm9uY2U=".to_owned())));
+    log_levels(&log);
+    assert!(log.html().contains("<span data-field=\"level\" class=\"slog-level-info\" \
+                                 role=\"img\" aria-label=\"info\"> INFO </span>"),
+            "{}",
+            log.html());
+}
+
+#[test]
+fn default_colors_pass_contrast_on_white() {
+    let warnings = slog_html::new().contrast_warnings();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    let palette = ColorPalette { warning: "ffaa00", ..ColorPalette::default() };
+    let warnings = slog_html::new().color_palette(palette).contrast_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].slot(), "warning level");
+    assert_eq!(warnings[0].to_string(),
+               "color #ffaa00 of warning level has a contrast of 1.91:1 against #ffffff, \
+                below 4.5:1");
+}
+
+#[test]
+fn lane_and_guide_colors_are_checked() {
+    let warnings = slog_html::new().background("1e1e1e").contrast_warnings();
+    for slot in &["context lane or merge source", "tree guide", "error level"] {
+        assert!(warnings.iter().any(|warning| warning.slot() == *slot), "{}", slot);
+    }
+
+    let builder = slog_html::new().background("1e1e1e").adjust_contrast(true);
+    let log = TestLog::new(builder.compact().context_lanes(true).tree_guides(true));
+    info!(log.logger().new(o!("request" => 1)), "nested");
+    let html = log.html();
+    assert!(!html.contains("color:#1f77b4;"), "{}", html);
+    assert!(!html.contains("color:#767676;"), "{}", html);
+    assert!(html.contains("<span data-lane=\"0\" style=\"color:#"), "{}", html);
+}
+
+#[test]
+fn contrast_is_adjusted_against_the_background() {
+    let palette = ColorPalette { warning: "ffaa00", ..ColorPalette::default() };
+    let log = TestLog::new(slog_html::new().color_palette(palette).adjust_contrast(true));
+    log_levels(&log);
+    let html = log.html();
+    assert!(!html.contains("color:#ffaa00;"), "{}", html);
+    // Colors with enough contrast are kept
+    assert!(html.contains("color:#55557f;"), "{}", html);
+
+    let dark = slog_html::new()
+        .background("1e1e1e")
+        .key_style(Style { color: Some("000080"), ..Style::default() });
+    let warnings = dark.contrast_warnings();
+    assert!(warnings.iter().any(|warning| warning.slot() == "key"));
+    assert!(warnings.iter().all(|warning| warning.ratio() < MIN_CONTRAST));
+    assert!(dark.adjust_contrast(true).contrast_warnings().is_empty());
+
+    let palette = ColorPalette { warning: "fff", ..ColorPalette::default() };
+    let log = TestLog::new(slog_html::new().color_palette(palette).adjust_contrast(true));
+    warn!(log.logger(), "short color code");
+    assert!(!log.html().contains("color:#fff;"));
+}
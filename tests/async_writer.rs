@@ -0,0 +1,232 @@
+//! Writing html on a background thread
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog::DrainExt;
+use slog_html::Overflow;
+use slog_html::testing::SharedBuffer;
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+const EPILOGUE: &str = "</div>\n</body>\n</html>\n";
+
+/// Writer held back until its gate is opened, marking flushes in its output
+struct Gated {
+    gate: Option<Receiver<()>>,
+    output: SharedBuffer,
+}
+
+impl Gated {
+    fn new(output: &SharedBuffer) -> (Self, Sender<()>) {
+        let (open, gate) = mpsc::channel();
+        (Gated { gate: Some(gate), output: output.clone() }, open)
+    }
+}
+
+impl io::Write for Gated {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(gate) = self.gate.take() {
+            let _ = gate.recv();
+        }
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.write_all(b"[flush]")
+    }
+}
+
+/// Writer failing every write
+struct Failing;
+
+impl io::Write for Failing {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer panicking on the first write
+struct Panicking;
+
+impl io::Write for Panicking {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        panic!("writer panicked on purpose")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Messages of the records in `html`, in order
+fn messages(html: &str) -> Vec<&str> {
+    html.split("<span data-field=\"msg\" dir=\"auto\" style=\"font-weight:bold;\">")
+        .skip(1)
+        .map(|rest| rest.split('<').next().unwrap())
+        .collect()
+}
+
+#[test]
+fn document_is_complete_once_the_drain_is_dropped() {
+    let output = SharedBuffer::new();
+    let drain = slog_html::async_writer(output.clone(), slog_html::new().build().unwrap())
+        .build()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    for i in 0..100 {
+        info!(log, "record {}", i);
+    }
+    drop(log);
+
+    let html = output.contents();
+    assert!(html.starts_with("<!DOCTYPE html>"), "{}", html);
+    assert!(html.ends_with(EPILOGUE), "{}", html);
+    let expected: Vec<String> = (0..100).map(|i| format!("record {}", i)).collect();
+    assert_eq!(messages(&html), expected);
+}
+
+#[test]
+fn output_is_flushed_after_the_epilogue() {
+    let output = SharedBuffer::new();
+    let (writer, open) = Gated::new(&output);
+    let drain = slog_html::async_writer(writer, slog_html::new().build().unwrap())
+        .build()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    info!(log, "queued");
+    open.send(()).unwrap();
+    drop(log);
+
+    let html = output.contents();
+    assert!(html.ends_with(&format!("{}[flush]", EPILOGUE)), "{}", html);
+}
+
+#[test]
+fn blocking_overflow_keeps_every_record() {
+    let output = SharedBuffer::new();
+    let drain = slog_html::async_writer(output.clone(), slog_html::new().build().unwrap())
+        .capacity(1)
+        .overflow(Overflow::Block)
+        .document(false)
+        .build()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    for i in 0..50 {
+        info!(log, "record {}", i);
+    }
+    drop(log);
+
+    let html = output.contents();
+    assert!(!html.contains("<!DOCTYPE html>"), "{}", html);
+    assert_eq!(messages(&html).len(), 50);
+}
+
+#[test]
+fn dropping_overflow_drops_records_while_full() {
+    let output = SharedBuffer::new();
+    // The writer thread is held back before writing the prologue, so one record fits
+    let (writer, open) = Gated::new(&output);
+    let drain = slog_html::async_writer(writer, slog_html::new().build().unwrap())
+        .capacity(1)
+        .overflow(Overflow::Drop)
+        .build()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    for i in 0..5 {
+        info!(log, "record {}", i);
+    }
+    open.send(()).unwrap();
+    drop(log);
+
+    let html = output.contents();
+    assert_eq!(messages(&html), vec!["record 0"]);
+    assert!(html.contains(EPILOGUE), "{}", html);
+}
+
+#[test]
+fn counted_overflow_reports_dropped_records() {
+    let output = SharedBuffer::new();
+    let (writer, open) = Gated::new(&output);
+    let drain = slog_html::async_writer(writer, slog_html::new().build().unwrap())
+        .capacity(1)
+        .overflow(Overflow::DropCounted)
+        .build()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    for i in 0..5 {
+        info!(log, "record {}", i);
+    }
+    open.send(()).unwrap();
+    // The count is reported on shutdown, if no later record reported it
+    drop(log);
+
+    let html = output.contents();
+    assert_eq!(messages(&html), vec!["record 0", "records dropped"]);
+    assert!(html.contains(">count</span>: <span data-field=\"value\" dir=\"auto\">4<"),
+            "{}",
+            html);
+}
+
+#[test]
+fn guard_ends_the_document_before_the_drain_is_dropped() {
+    let output = SharedBuffer::new();
+    let (drain, guard) = slog_html::async_writer(output.clone(),
+                                                 slog_html::new().build().unwrap())
+        .build_with_guard()
+        .unwrap();
+    let log = slog::Logger::root(drain.ignore_err(), o!());
+    info!(log, "before shutdown");
+    guard.finish().unwrap();
+    // Records logged after shutdown fail, and are not written after the epilogue
+    info!(log, "after shutdown");
+    drop(log);
+
+    let html = output.contents();
+    assert_eq!(messages(&html), vec!["before shutdown"]);
+    assert!(html.ends_with(EPILOGUE), "{}", html);
+}
+
+#[test]
+fn finish_returns_the_error_of_the_writer() {
+    let (drain, guard) = slog_html::async_writer(Failing, slog_html::new().build().unwrap())
+        .build_with_guard()
+        .unwrap();
+    let log = slog::Logger::root(drain.ignore_err(), o!());
+    info!(log, "lost");
+    let err = guard.finish().unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+    drop(log);
+}
+
+#[test]
+fn finish_reports_a_panic_of_the_writer() {
+    let (drain, guard) = slog_html::async_writer(Panicking, slog_html::new().build().unwrap())
+        .build_with_guard()
+        .unwrap();
+    let err = guard.finish().unwrap_err();
+    assert_eq!(err.to_string(), "html writer thread panicked");
+    drop(drain);
+}
+
+#[test]
+fn epilogue_is_written_once_whichever_is_dropped_first() {
+    let output = SharedBuffer::new();
+    let (drain, guard) = slog_html::async_writer(output.clone(),
+                                                 slog_html::new().build().unwrap())
+        .build_with_guard()
+        .unwrap();
+    let log = slog::Logger::root(drain.fuse(), o!());
+    info!(log, "record");
+    drop(log);
+    let html = output.contents();
+    assert!(html.ends_with(EPILOGUE), "{}", html);
+    drop(guard);
+    assert_eq!(output.contents(), html);
+}
@@ -0,0 +1,171 @@
+//! Helpers checking html output for well-formed html5, shared by the test files
+#![allow(dead_code)]
+
+use html5ever::tendril::TendrilSink;
+use html5ever::tokenizer::TokenizerOpts;
+use html5ever::tree_builder::TreeBuilderOpts;
+use html5ever::{ParseOpts, parse_document};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use slog::{Drain, DrainExt, Logger, OwnedKeyValueList, Record};
+use slog_html::{ContextDiff, DocumentDecorator, Format, FormatBuilder, Linkifier,
+                MarkupDecorator, Style, Truncation};
+use slog_stream::{Decorator, Format as StreamFormat};
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Elements the formatters may write, with those the parser adds around table rows
+pub const ELEMENTS: &[&str] = &["html", "head", "meta", "body", "pre", "span",
+                                "details", "summary", "a", "br", "h3", "table",
+                                "tbody", "tr", "td", "style", "link",
+                                "div"];
+
+/// Attributes the formatters may write, besides `data-*` ones
+pub const ATTRIBUTES: &[&str] = &["charset", "style", "href", "role", "width",
+                                  "cellpadding", "cellspacing", "border", "class",
+                                  "nonce", "rel", "aria-label", "dir"];
+
+/// Text no log line should turn into markup
+pub const HOSTILE: &[&str] = &["</pre><script>alert(1)</script>",
+                               "\" onmouseover=\"alert(1)",
+                               "' onfocus='alert(1)",
+                               "<!-- unterminated comment",
+                               "]]> <![CDATA[",
+                               "&amp; &lt;b&gt; &#0; &",
+                               "</details></summary></span>",
+                               "line one\nline two\r\nline three",
+                               "{\"html\": \"</pre>\", \"list\": [1, \"<b>\"]}",
+                               "https://example.com/?q=\"><script>",
+                               "\u{202e}reversed\u{202c} \u{1f600} \u{a0}",
+                               "\u{0}\u{1b}[31m\u{7f}\u{85}\u{fdd0}\u{ffff}\u{10ffff}"];
+
+/// Drain formatting into a buffer, keeping the format for the document prologue and epilogue
+pub struct Capture<D: Decorator>
+    where D::RecordDecorator: MarkupDecorator
+{
+    format: Arc<Format<D>>,
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+impl<D> Drain for Capture<D>
+    where D: Decorator,
+          D::RecordDecorator: MarkupDecorator
+{
+    type Error = io::Error;
+
+    fn log(&self, record: &Record, logger_values: &OwnedKeyValueList) -> io::Result<()> {
+        let mut output = self.output.lock().unwrap();
+        self.format.format(&mut *output, record, logger_values)
+    }
+}
+
+/// Render the records `log` writes as a document
+pub fn document<D>(format: Format<D>, log: &dyn Fn(&Logger)) -> String
+    where D: DocumentDecorator + Send + Sync + 'static,
+          D::RecordDecorator: MarkupDecorator
+{
+    let format = Arc::new(format);
+    let output = Arc::new(Mutex::new(Vec::new()));
+    format.write_prologue(&mut *output.lock().unwrap()).unwrap();
+    let drain = Capture {
+        format: format.clone(),
+        output: output.clone(),
+    };
+    log(&Logger::root(drain.fuse(), o!("version" => "1.0")));
+    let mut output = output.lock().unwrap();
+    format.write_epilogue(&mut *output).unwrap();
+    String::from_utf8(output.clone()).unwrap()
+}
+
+/// Assert that `html` parses without errors, and only has the expected elements and
+/// attributes
+pub fn check(html: &str) {
+    let opts = ParseOpts {
+        tokenizer: TokenizerOpts { exact_errors: true, ..Default::default() },
+        tree_builder: TreeBuilderOpts { exact_errors: true, ..Default::default() },
+    };
+    let dom = parse_document(RcDom::default(), opts).one(html);
+    assert!(dom.errors.is_empty(),
+            "parse errors {:?} in:\n{}",
+            dom.errors,
+            html);
+    check_node(&dom.document, html);
+}
+
+fn check_node(node: &Handle, html: &str) {
+    if let NodeData::Element { ref name, ref attrs, .. } = node.data {
+        let element = &*name.local;
+        assert!(ELEMENTS.contains(&element), "unexpected <{}> in:\n{}", element, html);
+        for attr in attrs.borrow().iter() {
+            let attribute = &*attr.name.local;
+            assert!(attribute.starts_with("data-") || ATTRIBUTES.contains(&attribute),
+                    "unexpected attribute {} of <{}> in:\n{}",
+                    attribute,
+                    element,
+                    html);
+        }
+    }
+    for child in node.children.borrow().iter() {
+        check_node(child, html);
+    }
+}
+
+/// Log hostile text as messages, keys, values and logger values, nested two levels deep
+pub fn log_hostile(root: &Logger) {
+    for (i, &text) in HOSTILE.iter().enumerate() {
+        let key: &'static str = Box::leak(text.to_owned().into_boxed_str());
+        let child = root.new(o!("text" => text, "i" => i));
+        let grandchild = child.new(o!(key => i));
+        info!(grandchild, "value" => text, key => text; "{}", text);
+        warn!(child, "debug" => format!("{:#?}", (text, [i, i + 1])); "{}", text);
+        error!(root, "hostile text"; "value" => text);
+    }
+}
+
+/// Builder of a format to check
+pub type Builder = Box<dyn Fn() -> FormatBuilder>;
+
+/// Builders covering every mode and option
+pub fn builders() -> Vec<(&'static str, Builder)> {
+    fn all(builder: FormatBuilder) -> FormatBuilder {
+        builder.elapsed_time(true)
+            .delta_time(true)
+            .context_delta_time(true)
+            .gap_threshold(Duration::from_millis(0))
+            .structured_values(true)
+            .linkify(Linkifier::new().file_paths(true))
+            .collapse_lines(1)
+            .max_message_len(12)
+            .max_value_len(8)
+            .message_width(16)
+            .columns(true)
+    }
+    vec![("full", Box::new(slog_html::new)),
+         ("compact", Box::new(|| slog_html::new().compact())),
+         ("full with all options", Box::new(|| all(slog_html::new()))),
+         ("full dropping truncated text",
+          Box::new(|| all(slog_html::new()).truncation(Truncation::Drop))),
+         ("compact with all options",
+          Box::new(|| {
+              all(slog_html::new().compact())
+                  .context_diff(ContextDiff::Dim)
+                  .tree_guides(true)
+          })),
+         ("compact with lanes",
+          Box::new(|| {
+              all(slog_html::new().compact())
+                  .context_lanes(true)
+                  .context_diff(ContextDiff::Omit)
+                  .context_cache(true)
+          })),
+         ("styled",
+          Box::new(|| {
+              slog_html::new()
+                  .compact()
+                  .level_style(Style { bold: true, italic: true, ..Style::default() })
+                  .key_style(Style { color: Some("123456"), ..Style::default() })
+                  .value_style(Style { custom: Some("background-color: #ffff7f;"),
+                                       ..Style::default() })
+          }))]
+}
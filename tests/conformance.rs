@@ -0,0 +1,152 @@
+//! Output of every mode and option checked for well-formed html5
+
+extern crate html5ever;
+extern crate markup5ever_rcdom;
+extern crate proptest;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+mod common;
+
+use proptest::collection;
+use proptest::prelude::*;
+use slog::{DrainExt, Logger};
+use slog_html::{Linkifier, Merger, Truncation};
+
+use std::ops::Range;
+
+use common::{HOSTILE, builders, check, document, log_hostile};
+
+/// Key as `parse` reads it back, with control characters and noncharacters made visible
+fn visible(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            '\u{0}'..='\u{1f}' => std::char::from_u32(0x2400 + c as u32).unwrap(),
+            '\u{7f}' => '\u{2421}',
+            '\u{80}'..='\u{9f}' | '\u{fdd0}'..='\u{fdef}' => '\u{fffd}',
+            c if c as u32 & 0xfffe == 0xfffe => '\u{fffd}',
+            c => c,
+        })
+        .collect()
+}
+
+/// Message or value as `parse` reads it back, also without trailing line breaks and with
+/// line breaks as `\n`
+fn read_back(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_end_matches(&['\n', '\r'][..]).lines().collect();
+    visible(&lines.join("\n"))
+}
+
+#[test]
+fn html_is_well_formed() {
+    for (name, builder) in builders() {
+        let html = document(builder().build().unwrap(), &log_hostile);
+        check(&html);
+        assert!(!html.contains("<script>"), "{}", name);
+    }
+}
+
+#[test]
+fn email_is_well_formed() {
+    for (_, builder) in builders() {
+        check(&document(builder().build_email().unwrap(), &log_hostile));
+    }
+}
+
+#[test]
+fn digest_is_well_formed() {
+    for (_, builder) in builders() {
+        let digest = slog_html::digest(builder().build_email().unwrap())
+            .level(slog::Level::Info)
+            .group_by("text")
+            .title("</h3><script>")
+            .build();
+        log_hostile(&Logger::root(digest.clone().fuse(), o!()));
+        let mut html = Vec::new();
+        digest.render(&mut html).unwrap();
+        check(&String::from_utf8(html).unwrap());
+    }
+}
+
+#[test]
+fn html_keeps_hostile_text() {
+    let format = slog_html::new().structured_values(false).build().unwrap();
+    let html = document(format, &log_hostile);
+    let records = slog_html::parse(&html).unwrap();
+    for (i, &text) in HOSTILE.iter().enumerate() {
+        let record = &records[3 * i];
+        assert_eq!(record.msg, read_back(text));
+        assert!(record.values.contains(&(visible(text), read_back(text))));
+        assert_eq!(records[3 * i + 2].values.last().unwrap().1, read_back(text));
+    }
+}
+
+#[test]
+fn converted_and_merged_logs_are_well_formed() {
+    let mut ansi = Vec::new();
+    for &text in HOSTILE {
+        ansi.extend_from_slice(b"Oct 19 04:37:51.497 \x1b[32mINFO\x1b[0m \x1b[1m");
+        ansi.extend_from_slice(text.replace('\n', " ").as_bytes());
+        ansi.extend_from_slice(b"\x1b[0m\n");
+    }
+    let mut converted = Vec::new();
+    slog_html::AnsiConverter::new().convert(&ansi[..], &mut converted).unwrap();
+    let converted = String::from_utf8(converted).unwrap();
+    check(&converted);
+
+    let logged = document(slog_html::new().compact().build().unwrap(), &log_hostile);
+    let mut merged = Vec::new();
+    Merger::new()
+        .source("\"><script>", converted)
+        .source("logged", logged)
+        .merge(&mut merged)
+        .unwrap();
+    check(&String::from_utf8(merged).unwrap());
+}
+
+/// Strings of any characters, including control characters and noncharacters
+fn text(len: Range<usize>) -> BoxedStrategy<String> {
+    collection::vec(any::<char>(), len).prop_map(|chars| chars.into_iter().collect()).boxed()
+}
+
+proptest! {
+    #[test]
+    fn random_pairs_are_well_formed(key in text(1..8),
+                                    value in text(0..32),
+                                    msg in text(0..32),
+                                    compact in any::<bool>()) {
+        let key: &'static str = Box::leak(key.into_boxed_str());
+        let builder = slog_html::new().structured_values(false);
+        let builder = if compact { builder.compact() } else { builder };
+        let log = |root: &Logger| {
+            let child = root.new(o!(key => value.clone()));
+            info!(child, key => value.clone(), "len" => value.len(); "{}", msg);
+        };
+
+        let html = document(builder.build().unwrap(), &log);
+        check(&html);
+        let records = slog_html::parse(&html).unwrap();
+        prop_assert_eq!(&records[0].msg, &read_back(&msg));
+        prop_assert!(records[0].values.contains(&(visible(key), read_back(&value))));
+    }
+
+    #[test]
+    fn random_options_are_well_formed(value in text(0..64),
+                                      option in 0..4usize,
+                                      len in 0..16usize) {
+        let builder = slog_html::new().structured_values(true);
+        let builder = match option {
+            0 => builder.collapse_lines(len),
+            1 => builder.max_value_len(len),
+            2 => builder.max_value_len(len).truncation(Truncation::Drop),
+            _ => builder.linkify(Linkifier::new().file_paths(true)),
+        };
+        let log = |root: &Logger| info!(root, "random"; "value" => value.clone());
+        check(&document(builder.build().unwrap(), &log));
+        let builder = slog_html::new().max_value_len(len).linkify(Linkifier::new());
+        check(&document(builder.build_email().unwrap(), &log));
+    }
+}
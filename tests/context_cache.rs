@@ -0,0 +1,37 @@
+//! Caching of serialized logger values in compact mode
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::FormatBuilder;
+use slog_html::testing::TestLog;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Log two records with a logger counting how often its lazy value is evaluated
+fn log_lazy(builder: FormatBuilder) -> String {
+    let log = TestLog::new(builder.compact());
+    let count = Arc::new(AtomicUsize::new(0));
+    let lazy = log.logger().new(o!("n" => move |_: &slog::Record| {
+        count.fetch_add(1, Ordering::SeqCst) + 1
+    }));
+    info!(lazy, "first");
+    info!(lazy, "second");
+    log.html()
+}
+
+#[test]
+fn lazy_values_are_evaluated_for_every_record_by_default() {
+    let html = log_lazy(slog_html::new());
+    assert!(html.contains(">1</span>"), "{}", html);
+    assert!(html.contains(">2</span>"), "{}", html);
+}
+
+#[test]
+fn lazy_values_are_evaluated_once_with_the_cache() {
+    let html = log_lazy(slog_html::new().context_cache(true));
+    assert!(html.contains(">1</span>"), "{}", html);
+    assert!(!html.contains(">2</span>"), "{}", html);
+}
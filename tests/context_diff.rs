@@ -0,0 +1,66 @@
+//! Differences between context headers in compact mode
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::ContextDiff;
+use slog_html::testing::TestLog;
+
+/// Text of each line, with dimmed pairs in brackets
+fn lines(html: &str) -> Vec<String> {
+    html.lines()
+        .map(|line| {
+            let line = line.replace("<span style=\"opacity:0.5;\">", "[")
+                .replace("</span></span>", "]");
+            let mut text = String::new();
+            let mut in_tag = false;
+            for c in line.chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    c if !in_tag => text.push(c),
+                    _ => {}
+                }
+            }
+            text
+        })
+        .filter(|line| !line.contains(" INFO "))
+        .collect()
+}
+
+fn log_requests(context_diff: ContextDiff) -> TestLog {
+    let log = TestLog::new(slog_html::new().compact().context_diff(context_diff));
+    let api = log.logger().new(o!("svc" => "api")).new(o!("req" => 1, "user" => "ann"));
+    let web = log.logger().new(o!("svc" => "web")).new(o!("req" => 1, "user" => "ann"));
+    let next = log.logger().new(o!("svc" => "web")).new(o!("req" => 2, "user" => "ann"));
+    info!(api, "x");
+    info!(web, "y");
+    info!(next, "z");
+    log
+}
+
+#[test]
+fn unchanged_pairs_are_dimmed() {
+    let log = log_requests(ContextDiff::Dim);
+    assert_eq!(lines(&log.html()),
+               vec!["svc: api", "  user: ann, req: 1", "svc: web", "  [user: ann], [req: 1]",
+                    "  [user: ann], req: 2"]);
+    log.assert_record(slog::Level::Info, "user", "ann");
+}
+
+#[test]
+fn unchanged_pairs_are_omitted_unless_no_pair_changed() {
+    let log = log_requests(ContextDiff::Omit);
+    assert_eq!(lines(&log.html()),
+               vec!["svc: api", "  user: ann, req: 1", "svc: web", "  [user: ann], [req: 1]",
+                    "  req: 2"]);
+}
+
+#[test]
+fn headers_are_not_compared_when_off() {
+    let log = log_requests(ContextDiff::Off);
+    assert_eq!(lines(&log.html()),
+               vec!["svc: api", "  user: ann, req: 1", "svc: web", "  user: ann, req: 1",
+                    "  user: ann, req: 2"]);
+}
@@ -0,0 +1,98 @@
+//! Content Security Policy mode, styling output by a stylesheet
+
+extern crate html5ever;
+extern crate markup5ever_rcdom;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+mod common;
+
+use slog::Logger;
+use slog_html::{ColorPalette, Csp, Style};
+
+use common::{builders, check, document, log_hostile};
+
+#[test]
+fn csp_output_has_no_inline_styles() {
+    let csp = Csp::Nonce("r4nd0m+/n0nce=".to_owned());
+    for (name, inline_builder) in builders() {
+        let builder = inline_builder().csp(csp.clone());
+        assert_eq!(builder.csp_header().unwrap(),
+                   "default-src 'none'; style-src 'nonce-r4nd0m+/n0nce='");
+        let html = document(builder.build().unwrap(), &log_hostile);
+        check(&html);
+        assert!(!html.contains(" style=\""), "{}: {}", name, html);
+        assert!(!html.contains("<script"), "{}", name);
+        assert!(html.contains("<style nonce=\"r4nd0m+/n0nce=\">\n.slog-line{"), "{}", name);
+
+        let inline = document(inline_builder().build().unwrap(), &log_hostile);
+        let records = |html: &str| {
+            slog_html::parse(html)
+                .unwrap()
+                .into_iter()
+                .map(|record| (record.msg, record.values))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(records(&html), records(&inline), "{}", name);
+    }
+}
+
+#[test]
+fn csp_rejects_colors_breaking_out_of_the_stylesheet() {
+    let hostile = "000;}</style><script>alert(1)</script><style>x{";
+    let csp = || slog_html::new().csp(Csp::Nonce("bm9uY2U=".to_owned()));
+    let style = Style { color: Some(hostile), ..Style::default() };
+    let palette = ColorPalette { warning: hostile, ..ColorPalette::default() };
+    let builders = [csp().level_style(style),
+                    csp().gap_style(style),
+                    csp().color_palette(palette),
+                    csp().background(hostile).adjust_contrast(true),
+                    csp().fragment("log").color_palette(palette),
+                    slog_html::new().csp(Csp::Stylesheet("log.css".to_owned())).key_style(style)];
+    for builder in builders {
+        assert_eq!(builder.build().err().unwrap().css(), hostile);
+    }
+
+    // Converters get invalid colors left out
+    let mut html = Vec::new();
+    slog_html::AnsiConverter::new()
+        .color_palette(palette)
+        .convert(&b"\x1b[33mWARN\x1b[0m x\n"[..], &mut html)
+        .unwrap();
+    let html = String::from_utf8(html).unwrap();
+    check(&html);
+    assert!(!html.contains("<script") && !html.contains("000;"), "{}", html);
+}
+
+#[test]
+fn csp_stylesheet_is_linked() {
+    let url = "https://cdn.example.com/logs/log.css?v=\"1\"";
+    let style = Style { custom: Some("font-family: 'Fira Code', monospace"), ..Style::default() };
+    let builder = slog_html::new().value_style(style).csp(Csp::Stylesheet(url.to_owned()));
+    assert_eq!(builder.csp_header().unwrap(),
+               "default-src 'none'; style-src https://cdn.example.com");
+    let format = builder.build().unwrap();
+    let mut stylesheet = Vec::new();
+    format.write_stylesheet(&mut stylesheet).unwrap();
+    let stylesheet = String::from_utf8(stylesheet).unwrap();
+    assert!(stylesheet.contains(".slog-value{font-family:'Fira Code', monospace;}\n"),
+            "{}",
+            stylesheet);
+
+    let log = |root: &Logger| info!(root, "styled"; "key" => "value");
+    let html = document(format, &log);
+    check(&html);
+    assert!(html.contains("<link rel=\"stylesheet\" \
+                           href=\"https://cdn.example.com/logs/log.css?v=&quot;1&quot;\">"),
+            "{}",
+            html);
+    assert!(html.contains("<span data-field=\"value\" dir=\"auto\" \
+                           class=\"slog-value\">value</span>"));
+    assert!(!html.contains(" style=\""), "{}", html);
+
+    assert_eq!(Csp::Stylesheet("log.css".to_owned()).header(),
+               "default-src 'none'; style-src 'self'");
+    assert!(slog_html::new().csp_header().is_none());
+}
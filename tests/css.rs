@@ -0,0 +1,93 @@
+//! Validation of the colors and custom CSS of styles
+
+extern crate html5ever;
+extern crate markup5ever_rcdom;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+mod common;
+
+use slog::Logger;
+use slog_html::{ColorPalette, Style};
+
+use common::{check, document};
+
+#[test]
+fn invalid_custom_css_is_rejected() {
+    let invalid = ["color: red\" onmouseover=\"alert(1)",
+                   "color: red'><script>alert(1)</script>",
+                   "background: url(javascript:alert(1))",
+                   "background-color: url(https://example.com/)",
+                   "color: expression(alert(1))",
+                   "position: fixed",
+                   "color: red; } body { color: blue",
+                   "font-family: 'unterminated",
+                   "color: \\72 ed",
+                   "color: /* comment */ red",
+                   "@import 'x'",
+                   "color"];
+    for &css in &invalid {
+        let style = Style { custom: Some(css), ..Style::default() };
+        let error = slog_html::new().value_style(style).build().err().expect(css);
+        assert_eq!(error.css(), css);
+        assert!(slog_html::new().gap_style(style).build_email().is_err(), "{}", css);
+    }
+}
+
+/// Colors that are not hexadecimal color codes
+const INVALID_COLORS: [&str; 6] = ["000;position:fixed;top:0",
+                                   "000\" onmouseover=\"alert(1)",
+                                   "#000000",
+                                   "red",
+                                   "00000",
+                                   ""];
+
+#[test]
+fn invalid_style_colors_are_rejected() {
+    for &color in &INVALID_COLORS {
+        let style = Style { color: Some(color), ..Style::default() };
+        let error = slog_html::new().key_style(style).build().err().expect(color);
+        assert_eq!(error.css(), color);
+        assert!(slog_html::new().duration_style(style).build_email().is_err(), "{}", color);
+    }
+    let style = Style { color: Some("fA0"), ..Style::default() };
+    assert!(slog_html::new().key_style(style).build().is_ok());
+}
+
+#[test]
+fn invalid_palette_colors_are_rejected() {
+    for &color in &INVALID_COLORS {
+        let palette = ColorPalette { trace: color, ..ColorPalette::default() };
+        let error = slog_html::new().color_palette(palette).build().err().expect(color);
+        assert_eq!(error.css(), color);
+        assert!(slog_html::new().color_palette(palette).build_email().is_err(), "{}", color);
+    }
+}
+
+#[test]
+fn invalid_backgrounds_are_rejected() {
+    for &color in &INVALID_COLORS {
+        let error = slog_html::new().background(color).build().err().expect(color);
+        assert_eq!(error.css(), color);
+        let builder = slog_html::new().background(color).adjust_contrast(true);
+        assert!(builder.build_email().is_err(), "{}", color);
+    }
+}
+
+#[test]
+fn custom_css_is_normalized() {
+    let style = Style {
+        custom: Some("Background-Color: #ffff7f; font-family: 'Fira Code', monospace;\n\
+                      border: 1px solid rgb(0, 0, 0) !important"),
+        ..Style::default()
+    };
+    let log = |root: &Logger| info!(root, "styled"; "key" => "value");
+    let html = document(slog_html::new().value_style(style).build().unwrap(), &log);
+    check(&html);
+    assert!(html.contains("style=\"background-color:#ffff7f;font-family:&#39;Fira Code&#39;, \
+                           monospace;border:1px solid rgb(0, 0, 0) !important;\""),
+            "{}",
+            html);
+}
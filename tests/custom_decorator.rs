@@ -0,0 +1,89 @@
+//! Formats with decorators of other crates
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::DrainExt;
+use slog_html::{Format, FormatMode, MarkupDecorator};
+use slog_html::testing::SharedBuffer;
+use slog_stream::{Decorator, RecordDecorator};
+
+use std::io;
+
+/// Decorator writing text as is, knowing nothing of `MarkupDecorator`
+struct TextDecorator;
+
+struct TextRecordDecorator;
+
+impl Decorator for TextDecorator {
+    type RecordDecorator = TextRecordDecorator;
+
+    fn decorate(&self, _: &slog::Record) -> TextRecordDecorator {
+        TextRecordDecorator
+    }
+}
+
+impl RecordDecorator for TextRecordDecorator {}
+
+/// Decorator writing lines as paragraphs
+struct ParagraphDecorator;
+
+struct ParagraphRecordDecorator;
+
+impl Decorator for ParagraphDecorator {
+    type RecordDecorator = ParagraphRecordDecorator;
+
+    fn decorate(&self, _: &slog::Record) -> ParagraphRecordDecorator {
+        ParagraphRecordDecorator
+    }
+}
+
+impl RecordDecorator for ParagraphRecordDecorator {}
+
+impl MarkupDecorator for ParagraphRecordDecorator {
+    fn fmt_line(&self,
+                io: &mut dyn io::Write,
+                f: &dyn Fn(&mut dyn io::Write) -> io::Result<()>)
+                -> io::Result<()> {
+        io.write_all(b"<p>")?;
+        f(io)?;
+        io.write_all(b"</p>\n")
+    }
+}
+
+fn logger<D: Decorator + Send + Sync + 'static>(format: Format<D>) -> (slog::Logger, SharedBuffer) {
+    let buf = SharedBuffer::new();
+    let drain = slog_stream::stream(buf.clone(), format).fuse();
+    (slog::Logger::root(drain, o!()), buf)
+}
+
+fn timestamp() -> Box<slog_html::TimestampFn> {
+    Box::new(|io| io.write_all(b"TS"))
+}
+
+#[test]
+fn full_mode_lines_are_preformatted() {
+    let (log, buf) = logger(Format::new(FormatMode::Full, TextDecorator, timestamp()));
+    info!(log.new(o!("svc" => "api")), "served"; "status" => 200);
+    assert_eq!(buf.contents(),
+               "<pre style=\"margin-bottom:-0.5em\">TS INFO served, svc: api, status: 200</pre>\n");
+}
+
+#[test]
+fn compact_mode_headers_are_indented() {
+    let (log, buf) = logger(Format::new(FormatMode::Compact, TextDecorator, timestamp()));
+    info!(log.new(o!("svc" => "api")), "served"; "status" => 200);
+    assert_eq!(buf.contents(),
+               "<pre style=\"margin-bottom:-0.5em\">svc: api</pre>\n\
+                <pre style=\"margin-bottom:-0.5em\">  TS INFO served, status: 200</pre>\n");
+}
+
+#[test]
+fn markup_hooks_are_used_with_markup() {
+    let format = Format::with_markup(FormatMode::Full, ParagraphDecorator, timestamp());
+    let (log, buf) = logger(format);
+    info!(log, "served");
+    assert_eq!(buf.contents(), "<p>TS INFO served</p>\n");
+}
@@ -0,0 +1,155 @@
+//! Digest drain collecting records into a single document
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog::{DrainExt, Level};
+use slog_html::{Digest, DigestBuilder, HtmlDecorator};
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Digest builder with timestamps counting the calls of the timestamp function
+fn digest() -> DigestBuilder<HtmlDecorator> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let format = slog_html::new()
+        .use_custom_timestamp(move |io| write!(io, "T{}", calls.fetch_add(1, Ordering::SeqCst)))
+        .build()
+        .unwrap();
+    slog_html::digest(format)
+}
+
+fn render(digest: &Digest<HtmlDecorator>) -> String {
+    let mut html = Vec::new();
+    digest.render(&mut html).unwrap();
+    String::from_utf8(html).unwrap()
+}
+
+/// Text of the headings of a document
+fn headings(html: &str) -> Vec<&str> {
+    html.split("<h3>").skip(1).map(|heading| &heading[..heading.find("</h3>").unwrap()]).collect()
+}
+
+#[test]
+fn summary_timestamps_are_the_ones_of_the_records() {
+    let digest = digest().build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    error!(log, "first");
+    crit!(log, "second");
+    let html = render(&digest);
+    assert_eq!(headings(&html), vec!["Log digest: 1 CRITICAL, 1 ERROR (T0 \u{2013} T1)"]);
+    assert!(html.contains("<span data-field=\"ts\">T0</span>"));
+    assert!(html.contains("<span data-field=\"ts\">T1</span>"));
+}
+
+#[test]
+fn records_below_the_level_are_dropped() {
+    let digest = digest().level(Level::Warning).title("Warnings").build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    info!(log, "info");
+    warn!(log, "warning");
+    debug!(log, "debug");
+    error!(log, "error");
+    assert_eq!(digest.len(), 2);
+    let html = render(&digest);
+    assert_eq!(headings(&html), vec!["Warnings: 1 ERROR, 1 WARN (T0 \u{2013} T1)"]);
+    assert!(html.contains(">warning</span>") && html.contains(">error</span>"));
+    assert!(!html.contains(">info</span>") && !html.contains(">debug</span>"));
+}
+
+#[test]
+fn max_records_keeps_the_newest_and_counts_the_omitted() {
+    let digest = digest().max_records(2).build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    for i in 0..5 {
+        error!(log, "record {}", i);
+    }
+    assert_eq!(digest.len(), 2);
+    let html = render(&digest);
+    assert_eq!(headings(&html), vec!["Log digest: 2 ERROR, 3 more omitted (T3 \u{2013} T4)"]);
+    assert!(!html.contains(">record 2</span>"));
+    assert!(html.contains(">record 3</span>") && html.contains(">record 4</span>"));
+
+    digest.clear();
+    assert!(digest.is_empty());
+    assert_eq!(headings(&render(&digest)), vec!["Log digest: no records"]);
+}
+
+#[test]
+fn records_fall_out_of_the_window() {
+    let digest = digest().window(Duration::from_millis(200)).build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    error!(log, "old");
+    thread::sleep(Duration::from_millis(300));
+    error!(log, "new");
+    assert_eq!(digest.len(), 1);
+    let html = render(&digest);
+    assert_eq!(headings(&html), vec!["Log digest: 1 ERROR (T1 \u{2013} T1)"]);
+    assert!(!html.contains(">old</span>"));
+}
+
+#[test]
+fn groups_in_order_of_their_first_record() {
+    let digest = digest().group_by("request_id").group_by("trace_id").build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    let b = log.new(o!("request_id" => "b"));
+    error!(b, "b1");
+    error!(log, "none");
+    error!(log, "a1"; "request_id" => "a");
+    crit!(b, "b2");
+    // Record values take precedence over the values of the logger
+    error!(b, "a2"; "request_id" => "a");
+    error!(log, "t1"; "trace_id" => 7);
+
+    let html = render(&digest);
+    assert_eq!(headings(&html),
+               vec!["Log digest: 1 CRITICAL, 5 ERROR (T0 \u{2013} T5)",
+                    "request_id b: 2 records, worst CRITICAL (T0 \u{2013} T3)",
+                    "request_id a: 2 records, worst ERROR (T2 \u{2013} T4)",
+                    "trace_id 7: 1 record, worst ERROR (T5 \u{2013} T5)",
+                    "Ungrouped: 1 record, worst ERROR (T1 \u{2013} T1)"]);
+    let order: Vec<usize> = ["b1", "b2", "a1", "a2", "t1", "none"]
+        .iter()
+        .map(|msg| html.find(&format!(">{}</span>", msg)).unwrap())
+        .collect();
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(order, sorted);
+}
+
+/// Writer logging to a digest while it is rendered
+struct LoggingWriter {
+    log: slog::Logger,
+    logged: bool,
+}
+
+impl io::Write for LoggingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.logged {
+            self.logged = true;
+            error!(self.log, "logged while rendering");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn logging_is_not_blocked_while_rendering() {
+    let digest = digest().build();
+    let log = slog::Logger::root(digest.clone().fuse(), o!());
+    error!(log, "before");
+    let mut writer = LoggingWriter {
+        log: log.clone(),
+        logged: false,
+    };
+    digest.render(&mut writer).unwrap();
+    assert!(writer.logged);
+    assert_eq!(digest.len(), 2);
+}
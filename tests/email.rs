@@ -0,0 +1,105 @@
+//! Email-safe html output
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::DrainExt;
+use slog_html::{FormatBuilder, Linkifier};
+use slog_html::testing::SharedBuffer;
+
+/// CSS properties the email output may use, as documented on `EmailDecorator`
+const PROPERTIES: &[&str] = &["margin", "padding", "border-collapse", "border-bottom",
+                              "line-height", "white-space", "color", "font-family",
+                              "font-size", "font-weight", "font-style"];
+
+fn builder() -> FormatBuilder {
+    slog_html::new().use_custom_timestamp(|io| io.write_all(b"TS"))
+}
+
+/// Whole document of the records logged by `f`
+fn document<F: FnOnce(&slog::Logger)>(builder: FormatBuilder, f: F) -> String {
+    let buf = SharedBuffer::new();
+    let format = builder.build_email().unwrap();
+    let mut prologue = Vec::new();
+    let mut epilogue = Vec::new();
+    format.write_prologue(&mut prologue).unwrap();
+    format.write_epilogue(&mut epilogue).unwrap();
+    f(&slog::Logger::root(slog_stream::stream(buf.clone(), format).fuse(), o!("svc" => "a")));
+    String::from_utf8(prologue).unwrap() + &buf.contents() + &String::from_utf8(epilogue).unwrap()
+}
+
+/// Line of the only record logged by `f`
+fn line<F: FnOnce(&slog::Logger)>(builder: FormatBuilder, f: F) -> String {
+    let html = document(builder, f);
+    let start = html.find("<tr>").unwrap();
+    let end = html.rfind("</tr>").unwrap();
+    html[start..end].to_owned()
+}
+
+#[test]
+fn only_inline_styles_from_the_documented_properties() {
+    let html = document(builder()
+                            .compact()
+                            .context_lanes(true)
+                            .tree_guides(true)
+                            .elapsed_time(true)
+                            .delta_time(true),
+                        |log| {
+                            let log = log.new(o!("req" => 1));
+                            info!(log, "first"; "k" => "v");
+                            error!(log, "second"; "k" => "v");
+                        });
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+    assert!(!html.contains("<style"));
+    assert!(!html.contains("class="));
+    let mut styles = html.split(" style=\"").skip(1).peekable();
+    assert!(styles.peek().is_some());
+    for style in styles {
+        let style = &style[..style.find('"').unwrap()];
+        for declaration in style.split(';').filter(|d| !d.is_empty()) {
+            let property = &declaration[..declaration.find(':').unwrap()];
+            assert!(PROPERTIES.contains(&property),
+                    "{} in style=\"{}\"",
+                    property,
+                    style);
+        }
+    }
+}
+
+#[test]
+fn records_are_table_rows() {
+    let html = line(builder(), |log| warn!(log, "disk <full>"; "volume" => "/srv"));
+    assert!(html.starts_with("<tr><td style=\""));
+    assert!(html.contains("<span data-field=\"msg\" dir=\"auto\" style=\"font-weight:bold;\">disk \
+                           &lt;full&gt;</span>"));
+    assert!(html.contains(">volume</span>: <span data-field=\"value\" dir=\"auto\">/srv</span>"));
+    assert!(html.ends_with("</td>"));
+}
+
+#[test]
+fn line_breaks_are_br_elements() {
+    let html = line(builder(), |log| info!(log, "one\ntwo\r\nthree"));
+    assert!(html.contains(">one<br>two<br>three</span>"));
+    assert!(!html.contains("<details"));
+}
+
+#[test]
+fn truncated_text_is_dropped() {
+    let html = line(builder().max_value_len(5).max_message_len(3),
+                    |log| info!(log, "message"; "long" => "0123é56789"));
+    assert!(html.contains(">mes</span>\u{2026} (+4 bytes)"));
+    // The cut moves back to a character boundary
+    assert!(html.contains(">0123</span>\u{2026} (+7 bytes)"));
+    assert!(!html.contains("56789"));
+}
+
+#[test]
+fn links_in_messages_and_values() {
+    let html = line(builder().linkify(Linkifier::new().urls(true)),
+                    |log| info!(log, "see https://example.com/?a=1&b=2"; "docs" => "http://x.org"));
+    assert!(html.contains("see <a href=\"https://example.com/?a=1&amp;b=2\">\
+                           https://example.com/?a=1&amp;b=2</a>"));
+    assert!(html.contains("<a href=\"http://x.org\">http://x.org</a>"));
+}
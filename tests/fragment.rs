@@ -0,0 +1,76 @@
+//! Fragments of html output embedded into other pages
+
+extern crate html5ever;
+extern crate markup5ever_rcdom;
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+mod common;
+
+use slog::Logger;
+use slog_html::Csp;
+
+use common::{HOSTILE, builders, check, document, log_hostile};
+
+#[test]
+fn fragments_coexist_on_one_page() {
+    let mut head = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let mut body = String::from("</head>\n<body>\n<h3>Report</h3>\n");
+    let prefixes = ["build-log", "deploy_log"];
+    for (&prefix, (name, builder)) in prefixes.iter().cycle().zip(builders()) {
+        let format = builder().fragment(prefix).build().unwrap();
+        let mut stylesheet = Vec::new();
+        format.write_stylesheet(&mut stylesheet).unwrap();
+        let stylesheet = String::from_utf8(stylesheet).unwrap();
+        for rule in stylesheet.lines() {
+            assert!(rule.starts_with(&format!(".{0} .{0}-", prefix)),
+                    "{}: global rule {}",
+                    name,
+                    rule);
+        }
+        head.push_str(&format!("<style>\n{}</style>\n", stylesheet));
+
+        let fragment = document(format, &log_hostile);
+        assert!(fragment.starts_with(&format!("<div class=\"{}\" role=\"log\">\n<pre ", prefix)),
+                "{}",
+                name);
+        assert!(fragment.ends_with("</div>\n"), "{}", name);
+        assert!(!fragment.contains("<style") && !fragment.contains("<link"), "{}", name);
+        assert!(!fragment.contains(" style=\""), "{}: {}", name, fragment);
+        assert!(!fragment.contains("<script"), "{}", name);
+        body.push_str(&fragment);
+    }
+    let page = head + &body + "</body>\n</html>\n";
+    check(&page);
+
+    let widgets = builders().len();
+    let records = slog_html::parse(&page).unwrap();
+    assert_eq!(records.len(), widgets * 3 * HOSTILE.len());
+}
+
+#[test]
+fn fragment_with_csp_nonce() {
+    let builder = slog_html::new().fragment("log").csp(Csp::Nonce("bm9uY2U=".to_owned()));
+    assert_eq!(builder.csp_header().unwrap(),
+               "default-src 'none'; style-src 'nonce-bm9uY2U='");
+    let log = |root: &Logger| info!(root, "embedded");
+    let format = builder.build().unwrap();
+    let mut stylesheet = Vec::new();
+    format.write_stylesheet(&mut stylesheet).unwrap();
+    assert!(String::from_utf8(stylesheet).unwrap().starts_with(".log .log-line{margin:0.5em 0}\n"));
+    let html = document(format, &log);
+    assert!(html.starts_with("<div class=\"log\" role=\"log\">\n<pre class=\"log-line\">"),
+            "{}",
+            html);
+}
+
+#[test]
+fn fragment_prefix_must_be_an_identifier() {
+    for prefix in &["log} body {color:red", "1log", "", "log.x"] {
+        let error = slog_html::new().fragment(prefix).build().err().expect(prefix);
+        assert_eq!(error.css(), *prefix);
+    }
+    assert!(slog_html::new().fragment("_log-2").build().is_ok());
+}
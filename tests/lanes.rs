@@ -0,0 +1,63 @@
+//! Context lanes in compact mode
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::testing::TestLog;
+
+/// Lane of each line, with its parent lane if it has one
+fn lanes(html: &str) -> Vec<(usize, Option<usize>)> {
+    html.lines()
+        .map(|line| {
+            let attribute = |name: &str| {
+                let start = line.find(&format!(" {}=\"", name))? + name.len() + 3;
+                line[start..].split('"').next()?.parse().ok()
+            };
+            (attribute("data-lane").expect("line has a lane"), attribute("data-parent"))
+        })
+        .collect()
+}
+
+#[test]
+fn known_contexts_keep_their_lane() {
+    let log = TestLog::new(slog_html::new().compact().context_lanes(true));
+    let a = log.logger().new(o!("conn" => 1));
+    let b = log.logger().new(o!("conn" => 2));
+    info!(a, "a1");
+    info!(b, "b1");
+    info!(a, "a2");
+    let request = a.new(o!("req" => 7));
+    info!(request, "a2 child");
+    let html = log.html();
+    // Headers of known contexts are not printed again
+    assert_eq!(html.matches(">conn</span>").count(), 2, "{}", html);
+    assert_eq!(lanes(&html),
+               vec![(0, None),
+                    (0, None),
+                    (1, Some(0)),
+                    (1, Some(0)),
+                    (0, None),
+                    (2, Some(0)),
+                    (2, Some(0))]);
+    assert!(html.contains("<span data-lane=\"0\" style=\"color:#1f77b4;\">\u{258c}</span> "),
+            "{}",
+            html);
+}
+
+#[test]
+fn forgotten_contexts_get_a_new_lane() {
+    let log = TestLog::new(slog_html::new().compact().context_lanes(true));
+    let first = log.logger().new(o!("conn" => 0));
+    info!(first, "first");
+    for i in 1..65 {
+        info!(log.logger().new(o!("conn" => i)), "other");
+    }
+    info!(first, "first again");
+    let html = log.html();
+    let lanes = lanes(&html);
+    // The context was evicted, so its header is printed again in a new lane
+    assert_eq!(&lanes[lanes.len() - 2..], &[(65, Some(64)), (65, Some(64))]);
+    assert_eq!(html.matches(">conn</span>: <span data-field=\"value\" dir=\"auto\">0<").count(),
+               2);
+}
@@ -0,0 +1,133 @@
+//! Layout of lines with text in any direction and of any width
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::testing::TestLog;
+
+/// Text of each record as displayed, without markup
+fn records(html: &str) -> Vec<String> {
+    html.split_terminator("</pre>\n")
+        .map(|record| {
+            let mut text = String::new();
+            let mut in_tag = false;
+            for c in record.chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    c if !in_tag => text.push(c),
+                    _ => {}
+                }
+            }
+            text
+        })
+        .collect()
+}
+
+/// Pairs after a message padded with `columns` columns
+fn padded(columns: usize, pairs: &str) -> String {
+    format!(", {}{}", " ".repeat(columns), pairs)
+}
+
+#[test]
+fn messages_keys_and_values_are_isolated() {
+    let log = TestLog::new(slog_html::new());
+    info!(log.logger(), "مرحبا بالعالم"; "\u{202e}key" => "שלום", "count" => 3);
+    let html = log.html();
+    assert!(html.contains("<span data-field=\"msg\" dir=\"auto\""), "{}", html);
+    assert_eq!(html.matches("<span data-field=\"key\" dir=\"auto\"").count(), 2, "{}", html);
+    assert_eq!(html.matches("<span data-field=\"value\" dir=\"auto\"").count(), 2, "{}", html);
+    log.assert_record(slog::Level::Info, "\u{202e}key", "שלום");
+}
+
+#[test]
+fn messages_are_padded_by_display_width() {
+    let log = TestLog::new(slog_html::new().message_width(20));
+    info!(log.logger(), "short"; "k" => 1);
+    info!(log.logger(), "日本語のメッセージ"; "k" => 2);
+    info!(log.logger(), "emoji \u{1f680}\u{1f680}"; "k" => 3);
+    info!(log.logger(), "e\u{301}te\u{301}"; "k" => 4);
+    info!(log.logger(), "no pairs");
+    let records = records(&log.html());
+    assert_eq!(records[0], format!("0001 INFO short{}", padded(15, "k: 1")));
+    assert_eq!(records[1], format!("0002 INFO 日本語のメッセージ{}", padded(2, "k: 2")));
+    assert_eq!(records[2],
+               format!("0003 INFO emoji \u{1f680}\u{1f680}{}", padded(10, "k: 3")));
+    assert_eq!(records[3], format!("0004 INFO e\u{301}te\u{301}{}", padded(17, "k: 4")));
+    assert_eq!(records[4], "0005 INFO no pairs");
+
+    // Padding is not part of any field
+    log.assert_record(slog::Level::Info, "k", "2");
+    log.assert_message(slog::Level::Info, "short");
+}
+
+#[test]
+fn padding_counts_truncation_and_collapsed_lines() {
+    let builder = slog_html::new().message_width(24).max_message_len(8).collapse_lines(1);
+    let log = TestLog::new(builder);
+    info!(log.logger(), "truncated message"; "k" => 1);
+    info!(log.logger(), "2\nlines"; "k" => 2);
+    let records = records(&log.html());
+    // `truncate… (+9 bytes)` is shown, the rest is expanded on click
+    assert_eq!(records[0],
+               format!("0001 INFO truncate\u{2026} (+9 bytes)d message{}",
+                       padded(4, "k: 1")));
+    // `… (+1 line)` is under the first line, and wider than it
+    assert_eq!(records[1],
+               format!("0002 INFO 2\u{2026} (+1 line)lines{}", padded(13, "k: 2")));
+
+    let log = TestLog::new(slog_html::new().message_width(8));
+    info!(log.logger(), "longer than the column"; "k" => 3);
+    let records = self::records(&log.html());
+    assert_eq!(records[0],
+               format!("0001 INFO longer than the column{}", padded(0, "k: 3")));
+}
+
+#[test]
+fn repeated_keys_line_up_in_columns() {
+    let log = TestLog::new(slog_html::new().message_width(8).columns(true));
+    info!(log.logger(), "start"; "user" => "ann", "port" => 80);
+    info!(log.logger(), "request"; "path" => "/", "user" => "bob", "port" => 8080);
+    info!(log.logger(), "done"; "port" => 1, "extra" => "x");
+    info!(log.logger(), "request"; "user" => "christine", "path" => "/index");
+    let records = records(&log.html());
+    // Keys are only given a column once they repeat
+    assert_eq!(records[0], format!("0001 INFO start{}", padded(3, "user: ann, port: 80")));
+    // `path` follows the columns until it repeats
+    assert_eq!(records[1],
+               format!("0002 INFO request{}", padded(1, "user: bob, port: 8080, path: /")));
+    // Missing pairs leave their column blank, other pairs are aligned after the columns
+    assert_eq!(records[2],
+               format!("0003 INFO done{}{}port: 1,    extra: x",
+                       padded(4, ""),
+                       " ".repeat(11)));
+    // Wider pairs widen their column for later records
+    assert_eq!(records[3],
+               format!("0004 INFO request{}{}path: /index",
+                       padded(1, "user: christine, "),
+                       " ".repeat(12)));
+
+    // Padding is not part of any field, and the pairs keep their keys
+    log.assert_record(slog::Level::Info, "path", "/index");
+    log.assert_record(slog::Level::Info, "extra", "x");
+}
+
+#[test]
+fn columns_count_logger_values_and_truncation() {
+    let log = TestLog::new(slog_html::new().columns(true).max_value_len(4));
+    let conn = log.logger().new(o!("conn" => 1));
+    info!(conn, "a"; "id" => "long value");
+    info!(conn, "b"; "id" => "v");
+    let records = records(&log.html());
+    assert_eq!(records[0], "0001 INFO a, conn: 1, id: long\u{2026} (+6 bytes) value");
+    // `conn` repeats first, so its column comes first
+    assert_eq!(records[1], "0002 INFO b, conn: 1, id: v");
+
+    info!(conn, "c"; "id" => "v");
+    info!(conn, "d"; "id" => "long value");
+    info!(conn, "e"; "id" => "v", "k" => 1);
+    let records = self::records(&log.html());
+    // `long… (+6 bytes)` is shown, the rest is expanded on click
+    assert_eq!(records[4], "0005 INFO e, conn: 1, id: v,                k: 1");
+}
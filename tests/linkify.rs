@@ -0,0 +1,77 @@
+//! Links in messages and values
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::Linkifier;
+use slog_html::testing::TestLog;
+
+fn html(linkifier: &Linkifier, text: &str) -> String {
+    let mut html = Vec::new();
+    linkifier.write_html(&mut html, text).unwrap();
+    String::from_utf8(html).unwrap()
+}
+
+#[test]
+fn urls_end_before_trailing_punctuation() {
+    let linkifier = Linkifier::new();
+    assert_eq!(html(&linkifier, "see https://example.com/a?b=1."),
+               "see <a href=\"https://example.com/a?b=1\">https://example.com/a?b=1</a>.");
+    assert_eq!(html(&linkifier, "(ftp://host/file), next"),
+               "(<a href=\"ftp://host/file\">ftp://host/file</a>), next");
+    // Quotes and angle brackets are not part of a URL
+    assert_eq!(html(&linkifier, "<http://a.example/x>\"y"),
+               "&lt;<a href=\"http://a.example/x\">http://a.example/x</a>&gt;&quot;y");
+    // Only the listed schemes are linked, starting at a word boundary
+    assert_eq!(html(&linkifier, "mailto:someone@example.com xhttp://no"),
+               "mailto:someone@example.com xhttp://no");
+}
+
+#[test]
+fn links_are_escaped() {
+    let linkifier = Linkifier::new();
+    assert_eq!(html(&linkifier, "http://example.com/?a=1&b=2"),
+               "<a href=\"http://example.com/?a=1&amp;b=2\">http://example.com/?a=1&amp;b=2</a>");
+    let linkifier = Linkifier::new()
+        .rule(r"#(\S+)", "https://example.com/search?q=$1&x=\"")
+        .unwrap();
+    assert_eq!(html(&linkifier, "tag #a<b"),
+               "tag <a href=\"https://example.com/search?q=a&lt;b&amp;x=&quot;\">#a&lt;b</a>");
+}
+
+#[test]
+fn unsafe_schemes_are_not_linked() {
+    let linkifier = Linkifier::new()
+        .rule(r"run:(\S+)", "$1")
+        .unwrap();
+    assert_eq!(html(&linkifier, "run:javascript:alert(1)"), "run:javascript:alert(1)");
+    assert_eq!(html(&linkifier, "run:JavaScript:x"), "run:JavaScript:x");
+    assert_eq!(html(&linkifier, "run:data:text/html,x"), "run:data:text/html,x");
+    assert_eq!(html(&linkifier, "run:/relative"),
+               "<a href=\"/relative\">run:/relative</a>");
+}
+
+#[test]
+fn file_paths_and_rules_do_not_overlap() {
+    let linkifier = Linkifier::new()
+        .file_paths(true)
+        .rule(r"\b[A-Z]+-\d+\b", "https://issues.example.com/browse/$0")
+        .unwrap();
+    assert_eq!(html(&linkifier, "read /etc/app/conf.toml. See OPS-12"),
+               "read <a href=\"file:///etc/app/conf.toml\">/etc/app/conf.toml</a>. See \
+                <a href=\"https://issues.example.com/browse/OPS-12\">OPS-12</a>");
+    // Paths need two components, and the path in a URL stays part of the URL
+    assert_eq!(html(&linkifier, "/tmp and http://host/a/b"),
+               "/tmp and <a href=\"http://host/a/b\">http://host/a/b</a>");
+    assert_eq!(html(&Linkifier::new().urls(false), "http://host/"), "http://host/");
+}
+
+#[test]
+fn messages_and_values_are_linked() {
+    let log = TestLog::new(slog_html::new().linkify(Linkifier::new()));
+    info!(log.logger(), "fetched https://example.com/"; "next" => "https://example.com/2");
+    let html = log.html();
+    assert_eq!(html.matches("<a href=\"https://example.com/").count(), 2, "{}", html);
+    log.assert_record(slog::Level::Info, "next", "https://example.com/2");
+}
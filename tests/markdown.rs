@@ -0,0 +1,61 @@
+//! GitHub flavored Markdown output
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::DrainExt;
+use slog_html::FormatBuilder;
+use slog_html::testing::SharedBuffer;
+
+fn logger(builder: FormatBuilder) -> (slog::Logger, SharedBuffer) {
+    let buf = SharedBuffer::new();
+    let format = builder.use_custom_timestamp(|io| io.write_all(b"TS")).build_markdown();
+    let drain = slog_stream::stream(buf.clone(), format).fuse();
+    (slog::Logger::root(drain, o!()), buf)
+}
+
+#[test]
+fn block_markers_in_continuation_lines() {
+    let (log, buf) = logger(slog_html::new());
+    info!(log, "stack:\n- frame one\n===\n1. x\n  10) y\n+ z\n> q");
+    assert_eq!(buf.contents(),
+               "TS \u{1f7e9} `INFO` **stack:\\\n\\- frame one\\\n\\===\\\n1\\. x\\\n  10\\) \
+                y\\\n\\+ z\\\n\\> q**\n\n");
+}
+
+#[test]
+fn markers_inside_a_line_are_kept() {
+    let (log, buf) = logger(slog_html::new());
+    info!(log, "- a - b\n2024 was 1.5 = 3/2");
+    assert_eq!(buf.contents(),
+               "TS \u{1f7e9} `INFO` **- a - b\\\n2024 was 1.5 = 3/2**\n\n");
+}
+
+#[test]
+fn inline_punctuation_is_escaped() {
+    let (log, buf) = logger(slog_html::new());
+    warn!(log, "*bold* _it_ [link](x) <b> #h ~s~ & \\ `code` a|b");
+    assert_eq!(buf.contents(),
+               "TS \u{1f7e8} `WARN` **\\*bold\\* \\_it\\_ \\[link\\](x) \\<b\\> \\#h \\~s\\~ \\& \
+                \\\\ \\`code\\` a\\|b**\n\n");
+}
+
+#[test]
+fn pairs_table_with_code_spans() {
+    let (log, buf) = logger(slog_html::new());
+    info!(log, "served"; "pipe" => "a|b", "tick" => "`x`", "lines" => "a\nb");
+    assert_eq!(buf.contents(),
+               "TS \u{1f7e9} `INFO` **served**\n\n| key | value |\n| --- | --- |\n\
+                | pipe | `a\\|b` |\n| tick | `` `x` `` |\n| lines | `a\\nb` |\n\n");
+}
+
+#[test]
+fn compact_context_headers_are_inline() {
+    let (log, buf) = logger(slog_html::new().compact());
+    let log = log.new(o!("svc" => "api", "req" => 1));
+    info!(log, "nested"; "k" => "v");
+    assert_eq!(buf.contents(),
+               "req: `1`, svc: `api`\n\n\u{2003}\u{2003}TS \u{1f7e9} `INFO` **nested**\n\n\
+                | key | value |\n| --- | --- |\n| k | `v` |\n\n");
+}
@@ -0,0 +1,173 @@
+//! Html logs of several sources merged into one timeline
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::DrainExt;
+use slog_html::{Csp, FormatBuilder, Merger};
+use slog_html::testing::SharedBuffer;
+
+use std::cell::RefCell;
+
+thread_local! {
+    /// Timestamp written for the next record
+    static TIMESTAMP: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Document of the records logged by `f`, which sets their timestamps with `at`
+fn source<F: FnOnce(&slog::Logger, &dyn Fn(&str))>(builder: FormatBuilder, f: F) -> String {
+    let buf = SharedBuffer::new();
+    let format = builder.use_custom_timestamp(|io| {
+            TIMESTAMP.with(|t| io.write_all(t.borrow().as_bytes()))
+        })
+        .build()
+        .unwrap();
+    let mut prologue = Vec::new();
+    let mut epilogue = Vec::new();
+    format.write_prologue(&mut prologue).unwrap();
+    format.write_epilogue(&mut epilogue).unwrap();
+    f(&slog::Logger::root(slog_stream::stream(buf.clone(), format).fuse(), o!()),
+      &|timestamp| TIMESTAMP.with(|t| *t.borrow_mut() = timestamp.to_owned()));
+    String::from_utf8(prologue).unwrap() + &buf.contents() + &String::from_utf8(epilogue).unwrap()
+}
+
+/// Source logging a record with message `msg` at each timestamp
+fn records(builder: FormatBuilder, records: &[(&str, &str)]) -> String {
+    source(builder, |log, at| {
+        for &(timestamp, msg) in records {
+            at(timestamp);
+            info!(log, "{}", msg);
+        }
+    })
+}
+
+fn merge(merger: Merger) -> String {
+    let mut html = Vec::new();
+    merger.merge(&mut html).unwrap();
+    String::from_utf8(html).unwrap()
+}
+
+/// Messages of the records of `html`, in order
+fn messages(html: &str) -> Vec<String> {
+    slog_html::parse(html).unwrap().into_iter().map(|record| record.msg).collect()
+}
+
+#[test]
+fn records_are_ordered_by_timestamp() {
+    let api = records(slog_html::new(),
+                      &[("Oct 19 04:37:51.100", "api 1"),
+                        ("Oct 19 04:37:51.300", "api 2"),
+                        ("Oct 19 04:37:51.300", "api 3")]);
+    let db = records(slog_html::new(),
+                     &[("Oct 19 04:37:51.000", "db 1"),
+                       ("Oct 19 04:37:51.300", "db 2"),
+                       ("Nov 02 00:00:00.000", "db 3")]);
+    let html = merge(Merger::new().source("api", api).source("db", db));
+    // Equal timestamps keep the order of the sources
+    assert_eq!(messages(&html), ["db 1", "api 1", "api 2", "api 3", "db 2", "db 3"]);
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.contains("<span data-source=\"api\" style=\"color:#"));
+}
+
+#[test]
+fn timestamps_without_a_year_wrap_into_the_next_year() {
+    let api = records(slog_html::new(),
+                      &[("Dec 31 23:59:58.000", "api 1"), ("Jan 01 00:00:01.000", "api 2")]);
+    // A source starting after the turn of the year
+    let db = records(slog_html::new(),
+                     &[("Jan 01 00:00:00.000", "db 1"), ("Feb 01 00:00:00.000", "db 2")]);
+    let html = merge(Merger::new().source("api", api.clone()).source("db", db.clone()));
+    assert_eq!(messages(&html), ["api 1", "db 1", "api 2", "db 2"]);
+    let html = merge(Merger::new().source("db", db).source("api", api));
+    assert_eq!(messages(&html), ["api 1", "db 1", "api 2", "db 2"]);
+}
+
+#[test]
+fn styles_of_class_styled_sources_are_inlined() {
+    let csp = records(slog_html::new().csp(Csp::Nonce("n0nce".to_owned())),
+                      &[("Oct 19 04:37:51.000", "csp")]);
+    let fragment = records(slog_html::new().fragment("frag"),
+                           &[("Oct 19 04:37:52.000", "fragment")]);
+    // A page embedding the fragment, with its stylesheet in the head
+    let mut stylesheet = Vec::new();
+    slog_html::new().fragment("frag").build().unwrap().write_stylesheet(&mut stylesheet).unwrap();
+    let fragment = format!("<!DOCTYPE html>\n<html>\n<head>\n<style>\n{}</style>\n</head>\n\
+                           <body>\n{}</body>\n</html>\n",
+                          String::from_utf8(stylesheet).unwrap(),
+                          fragment);
+    let html = merge(Merger::new().source("csp", csp).source("fragment", fragment));
+    assert!(!html.contains("class="));
+    assert!(!html.contains("<style"));
+    assert!(html.contains(" style=\"font-weight:bold;\">csp</span>"));
+    assert!(html.contains(" style=\"font-weight:bold;\">fragment</span>"));
+    assert_eq!(messages(&html), ["csp", "fragment"]);
+}
+
+#[test]
+fn linked_stylesheets_are_linked_once() {
+    let build = || slog_html::new().csp(Csp::Stylesheet("/log.css?a=1&b=2".to_owned()));
+    let a = records(build(), &[("Oct 19 04:37:51.000", "a")]);
+    let b = records(build(), &[("Oct 19 04:37:52.000", "b")]);
+    let html = merge(Merger::new().source("a", a).source("b", b));
+    let link = "<link rel=\"stylesheet\" href=\"/log.css?a=1&amp;b=2\">\n";
+    assert_eq!(html.matches(link).count(), 1);
+    assert!(html.find(link).unwrap() < html.find("</head>").unwrap());
+    assert!(html.contains(" class=\"slog-msg\">a</span>"));
+}
+
+#[test]
+fn context_headers_are_repeated_after_a_switch() {
+    let log = |req: &'static str, timestamps: [&'static str; 2]| {
+        source(slog_html::new().compact(), move |log, at| {
+            let log = log.new(o!("req" => req));
+            at(timestamps[0]);
+            info!(log, "{} 1", req);
+            at(timestamps[1]);
+            info!(log, "{} 2", req);
+        })
+    };
+    let a = log("a", ["Oct 19 04:37:51.000", "Oct 19 04:37:53.000"]);
+    let b = log("b", ["Oct 19 04:37:52.000", "Oct 19 04:37:54.000"]);
+    let html = merge(Merger::new().source("a", a).source("b", b));
+    assert_eq!(html.matches(">req</span>").count(), 4);
+    let records = slog_html::parse(&html).unwrap();
+    let context: Vec<(&str, &str)> = records.iter()
+        .map(|record| (record.msg.as_str(), record.values[0].1.as_str()))
+        .collect();
+    assert_eq!(context, [("a 1", "a"), ("b 1", "b"), ("a 2", "a"), ("b 2", "b")]);
+}
+
+#[test]
+fn lanes_of_different_sources_stay_apart() {
+    let log = |req: &'static str, timestamps: [&'static str; 2]| {
+        source(slog_html::new().compact().context_lanes(true), move |log, at| {
+            let log = log.new(o!("req" => req));
+            at(timestamps[0]);
+            info!(log, "{} 1", req);
+            at(timestamps[1]);
+            info!(log, "{} 2", req);
+        })
+    };
+    let a = log("a", ["Oct 19 04:37:51.000", "Oct 19 04:37:53.000"]);
+    let b = log("b", ["Oct 19 04:37:52.000", "Oct 19 04:37:54.000"]);
+    let html = merge(Merger::new().source("a", a).source("b", b));
+    // With lanes, headers are not repeated
+    assert_eq!(html.matches(">req</span>").count(), 2);
+    assert!(html.contains("data-lane=\"0\"") && html.contains("data-lane=\"1\""));
+    let records = slog_html::parse(&html).unwrap();
+    let context: Vec<(&str, &str)> = records.iter()
+        .map(|record| (record.msg.as_str(), record.values[0].1.as_str()))
+        .collect();
+    assert_eq!(context, [("a 1", "a"), ("b 1", "b"), ("a 2", "a"), ("b 2", "b")]);
+}
+
+#[test]
+fn labels_are_escaped() {
+    let api = records(slog_html::new(), &[("Oct 19 04:37:51.000", "x")]);
+    let html = merge(Merger::new().source("<api> & \"db\"", api).document(false));
+    assert!(html.starts_with("<pre style=\"margin-bottom:-0.5em\"><span \
+                              data-source=\"&lt;api&gt; &amp; &quot;db&quot;\""));
+    assert!(html.contains(">&lt;api&gt; &amp; &quot;db&quot;</span> "));
+}
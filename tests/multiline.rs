@@ -0,0 +1,47 @@
+//! Messages and values spanning several lines
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog::Level;
+use slog_html::testing::TestLog;
+
+const BLOCK: &str = "<span style=\"display:inline-block;vertical-align:top\">";
+
+#[test]
+fn lines_are_kept_in_an_aligned_block() {
+    let log = TestLog::new(slog_html::new());
+    info!(log.logger(), "one\r\ntwo\n"; "single" => "line", "multi" => "a\nb");
+    let html = log.html();
+    // Line endings are normalized and a trailing one is dropped
+    assert!(html.contains(&format!("{}<span data-field=\"msg\" dir=\"auto\" \
+                                    style=\"font-weight:bold;\">one\ntwo</span></span>",
+                                   BLOCK)),
+            "{}",
+            html);
+    assert_eq!(html.matches(BLOCK).count(), 2, "{}", html);
+    log.assert_message(Level::Info, "one\ntwo");
+    log.assert_record(Level::Info, "multi", "a\nb");
+}
+
+#[test]
+fn lines_are_collapsed_after_the_limit() {
+    let log = TestLog::new(slog_html::new().collapse_lines(2));
+    info!(log.logger(), "one\ntwo\nthree\nfour"; "v" => "a\nb");
+    let html = log.html();
+    assert!(html.contains("one\ntwo</span><details data-break><summary>\u{2026} (+2 lines)\
+                           </summary><span data-field=\"msg\" dir=\"auto\" \
+                           style=\"font-weight:bold;\">three\nfour</span></details>"),
+            "{}",
+            html);
+    // Values within the limit are not collapsed
+    assert_eq!(html.matches("<details").count(), 1, "{}", html);
+    log.assert_message(Level::Info, "one\ntwo\nthree\nfour");
+    log.assert_record(Level::Info, "v", "a\nb");
+
+    // At least the first line stays visible
+    let log = TestLog::new(slog_html::new().collapse_lines(0));
+    info!(log.logger(), "first\nsecond");
+    assert!(log.html().contains("first</span><details data-break><summary>\u{2026} (+1 line)"));
+}
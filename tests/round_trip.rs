@@ -0,0 +1,167 @@
+//! Html output read back with `parse` and formatted again
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+extern crate slog_stream;
+
+use slog::{BorrowedKeyValue, DrainExt, Level, OwnedKeyValueList, Record, RecordStatic};
+use slog_html::{Format, FormatBuilder, HtmlDecorator, Linkifier, ParsedRecord};
+use slog_html::testing::SharedBuffer;
+use slog_stream::Format as StreamFormat;
+
+use std::cell::RefCell;
+
+thread_local! {
+    /// Timestamp written for the next record
+    static TIMESTAMP: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn set_timestamp(timestamp: &str) {
+    TIMESTAMP.with(|t| *t.borrow_mut() = timestamp.to_owned());
+}
+
+fn builder() -> FormatBuilder {
+    slog_html::new().use_custom_timestamp(|io| TIMESTAMP.with(|t| io.write_all(t.borrow().as_bytes())))
+}
+
+fn logger(format: Format<HtmlDecorator>) -> (slog::Logger, SharedBuffer) {
+    let buf = SharedBuffer::new();
+    let drain = slog_stream::stream(buf.clone(), format).fuse();
+    (slog::Logger::root(drain, o!("service" => "api")), buf)
+}
+
+/// Format parsed records again, with all values as record values
+fn render(format: &Format<HtmlDecorator>, records: &[ParsedRecord]) -> String {
+    let mut buf = Vec::new();
+    for record in records {
+        let statics: &'static RecordStatic<'static> = Box::leak(Box::new(RecordStatic {
+            level: record.level,
+            file: file!(),
+            line: line!(),
+            column: 0,
+            function: "",
+            module: module_path!(),
+            target: module_path!(),
+        }));
+        let values: Vec<BorrowedKeyValue> = record.values
+            .iter()
+            .map(|(key, value)| {
+                let key: &'static str = Box::leak(key.clone().into_boxed_str());
+                (key, value as &dyn slog::ser::Serialize)
+            })
+            .collect();
+        set_timestamp(&record.timestamp);
+        // The only way to make an empty list of logger values in slog 1.x
+        #[allow(deprecated)]
+        let logger_values = OwnedKeyValueList::root(None);
+        format.format(&mut buf,
+                    &Record::new(statics, format_args!("{}", record.msg), &values),
+                    &logger_values)
+            .unwrap();
+    }
+    String::from_utf8(buf).unwrap()
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect()
+}
+
+#[test]
+fn full_mode_round_trip() {
+    let build = || {
+        builder()
+            .structured_values(true)
+            .linkify(Linkifier::new().urls(true))
+            .collapse_lines(2)
+            .max_value_len(24)
+            .build()
+            .unwrap()
+    };
+    let (log, buf) = logger(build());
+
+    set_timestamp("Oct 19 04:37:51.497");
+    info!(log, "request <served> & \"done\""; "path" => "/index.html?a=1&b=2", "status" => 200);
+    set_timestamp("Oct 19 04:37:52.001");
+    warn!(log, "first line\nsecond line\nthird line"; "url" => "see https://example.com/x");
+    set_timestamp("Oct 19 04:37:53.250");
+    error!(log, "structured"; "json" => r#"{"user": {"id": 7, "tags": ["a", "b"]}}"#,
+           "debug" => "Point {\n    x: 1,\n    y: 2,\n}", "long" => "0123456789".repeat(5));
+
+    let first = buf.contents();
+    let records = slog_html::parse(&first).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].timestamp, "Oct 19 04:37:51.497");
+    assert_eq!(records[0].level, Level::Info);
+    assert_eq!(records[0].msg, "request <served> & \"done\"");
+    assert_eq!(records[0].values,
+               pairs(&[("service", "api"), ("path", "/index.html?a=1&b=2"), ("status", "200")]));
+    assert_eq!(records[1].msg, "first line\nsecond line\nthird line");
+    assert_eq!(records[1].values[1].1, "see https://example.com/x");
+    assert_eq!(records[2].values,
+               pairs(&[("service", "api"),
+                       ("json", r#"{"user":{"id":7,"tags":["a","b"]}}"#),
+                       ("debug", "Point {\n    x: 1,\n    y: 2,\n}"),
+                       ("long", &"0123456789".repeat(5))]));
+
+    let second = render(&build(), &records);
+    assert_eq!(second, first);
+    assert_eq!(slog_html::parse(&second).unwrap(), records);
+}
+
+#[test]
+fn compact_mode_restores_context() {
+    let format = builder().compact().context_lanes(true).tree_guides(true).build().unwrap();
+    let (log, buf) = logger(format);
+    set_timestamp("TS");
+    let a = log.new(o!("request_id" => "a1"));
+    let b = log.new(o!("request_id" => "b2"));
+    let db = a.new(o!("db" => "main"));
+    info!(a, "a start");
+    info!(b, "b start"; "user" => "x");
+    debug!(db, "query");
+    info!(log, "root");
+
+    let records = slog_html::parse(&buf.contents()).unwrap();
+    let summary: Vec<(&str, Vec<(String, String)>)> =
+        records.iter().map(|record| (record.msg.as_str(), record.values.clone())).collect();
+    assert_eq!(summary,
+               vec![("a start", pairs(&[("service", "api"), ("request_id", "a1")])),
+                    ("b start",
+                     pairs(&[("service", "api"), ("request_id", "b2"), ("user", "x")])),
+                    ("query",
+                     pairs(&[("service", "api"), ("request_id", "a1"), ("db", "main")])),
+                    ("root", pairs(&[("service", "api")]))]);
+}
+
+#[test]
+fn document_with_timing_columns() {
+    let mut prologue = Vec::new();
+    let format = builder().elapsed_time(true).delta_time(true).build().unwrap();
+    format.write_prologue(&mut prologue).unwrap();
+    let (log, buf) = logger(format);
+    set_timestamp("TS");
+    trace!(log, "tick"; "n" => 1);
+    crit!(log, "boom");
+    let html = String::from_utf8(prologue).unwrap() + &buf.contents();
+
+    let records = slog_html::parse(&html).unwrap();
+    let levels: Vec<Level> = records.iter().map(|record| record.level).collect();
+    assert_eq!(levels, vec![Level::Trace, Level::Critical]);
+    assert_eq!(records[0].values, pairs(&[("service", "api"), ("n", "1")]));
+}
+
+#[test]
+fn json_lines() {
+    let record = ParsedRecord {
+        timestamp: "TS".to_owned(),
+        level: Level::Error,
+        msg: "failed: \"disk\"\n\tfull".to_owned(),
+        values: pairs(&[("path", "C:\\data"), ("bell", "\u{7}")]),
+    };
+    let mut json = Vec::new();
+    record.write_json(&mut json).unwrap();
+    assert_eq!(String::from_utf8(json).unwrap(),
+               "{\"ts\":\"TS\",\"level\":\"ERRO\",\"msg\":\"failed: \\\"disk\\\"\\n\\tfull\",\
+                \"path\":\"C:\\\\data\",\"bell\":\"\\u0007\"}\n");
+}
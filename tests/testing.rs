@@ -0,0 +1,104 @@
+//! Test helpers of `slog_html::testing`
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog::Level;
+use slog_html::testing::{TestLog, UPDATE_SNAPSHOTS, assert_snapshot, normalize};
+
+use std::{env, fs};
+use std::panic;
+
+fn log_requests(log: &TestLog) {
+    let api = log.logger().new(o!("service" => "api"));
+    let request = api.new(o!("request_id" => 7));
+    info!(request, "request received"; "path" => "/users");
+    warn!(request, "slow query"; "ms" => 1200, "table" => "users");
+    error!(api, "connection lost"; "peer" => "10.0.0.2");
+}
+
+#[test]
+fn assertions_find_records() {
+    let log = TestLog::new(slog_html::new());
+    log_requests(&log);
+
+    log.assert_message(Level::Warning, "slow query");
+    log.assert_record(Level::Warning, "ms", "1200");
+    log.assert_record(Level::Info, "request_id", "7");
+    log.assert_record(Level::Error, "service", "api");
+
+    let timestamps: Vec<String> =
+        log.records().into_iter().map(|record| record.timestamp).collect();
+    assert_eq!(timestamps, ["0001", "0002", "0003"]);
+}
+
+#[test]
+fn assertions_include_compact_context() {
+    let log = TestLog::new(slog_html::new().compact());
+    log_requests(&log);
+
+    log.assert_record(Level::Warning, "service", "api");
+    log.assert_record(Level::Warning, "request_id", "7");
+    log.assert_record(Level::Warning, "table", "users");
+}
+
+#[test]
+#[should_panic(expected = "no WARN record with ms = \"5\"")]
+fn assertion_failure_lists_records() {
+    let log = TestLog::new(slog_html::new());
+    log_requests(&log);
+    log.assert_record(Level::Warning, "ms", "5");
+}
+
+#[test]
+fn timing_columns_are_normalized() {
+    let log = TestLog::new(slog_html::new().elapsed_time(true).delta_time(true));
+    log_requests(&log);
+
+    let html = log.html();
+    assert!(html.contains("<span data-field=\"duration\">*</span>"));
+    assert_eq!(normalize(&html), html);
+    log.assert_message(Level::Error, "connection lost");
+}
+
+#[test]
+fn full_mode_snapshot() {
+    let log = TestLog::new(slog_html::new());
+    log_requests(&log);
+    log.assert_snapshot("tests/snapshots/full.html");
+}
+
+#[test]
+fn compact_mode_snapshot() {
+    let log = TestLog::new(slog_html::new().compact().tree_guides(true));
+    log_requests(&log);
+    log.assert_snapshot("tests/snapshots/compact.html");
+}
+
+#[test]
+fn snapshot_mismatch_shows_diff() {
+    let path = concat!(env!("CARGO_TARGET_TMPDIR"), "/mismatch.html");
+    fs::write(path, "first\nsecond\nthird\n").unwrap();
+
+    let panic = panic::catch_unwind(|| assert_snapshot(path, "first\nchanged\nthird\n"))
+        .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("-    2 | second\n"), "{}", message);
+    assert!(message.contains("+    2 | changed\n"), "{}", message);
+    assert!(message.contains("     1 | first\n"), "{}", message);
+}
+
+#[test]
+fn missing_snapshot_fails() {
+    if env::var_os(UPDATE_SNAPSHOTS).is_some() {
+        return;
+    }
+    let path = concat!(env!("CARGO_TARGET_TMPDIR"), "/missing.html");
+    let _ = fs::remove_file(path);
+
+    let panic = panic::catch_unwind(|| assert_snapshot(path, "output\n")).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("is missing"), "{}", message);
+    assert!(fs::metadata(path).is_err());
+}
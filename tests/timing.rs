@@ -0,0 +1,92 @@
+//! Elapsed and delta time columns
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog_html::Style;
+use slog_html::testing::TestLog;
+
+use std::thread;
+use std::time::Duration;
+
+/// Color of the gap style in tests
+const GAP_COLOR: &str = "123456";
+
+/// Threshold of gaps in tests, well above the time between records logged in a row
+const THRESHOLD: Duration = Duration::from_millis(50);
+
+/// Delta time column of a record without a previous record
+fn first_delta(label: &str) -> String {
+    format!(" {}{:>8}<", label, "-")
+}
+
+/// Test log highlighting gaps over the threshold in the gap color
+fn gap_log(builder: slog_html::FormatBuilder) -> TestLog {
+    TestLog::new(builder.gap_threshold(THRESHOLD)
+        .gap_style(Style { color: Some(GAP_COLOR), ..Style::default() }))
+}
+
+/// Whether the time column starting with `label` on `line` is highlighted as a gap
+fn is_gap(line: &str, label: &str) -> bool {
+    let gap = format!("style=\"color:#{};\"> {}", GAP_COLOR, label);
+    let duration = format!("style=\"color:#707070;\"> {}", label);
+    assert!(line.contains(&gap) != line.contains(&duration), "{}", line);
+    line.contains(&gap)
+}
+
+#[test]
+fn context_delta_is_per_logger() {
+    let log = TestLog::new(slog_html::new().context_delta_time(true));
+    let a = log.logger().new(o!("logger" => "a"));
+    let b = log.logger().new(o!("logger" => "b"));
+    info!(a, "first of a");
+    info!(b, "first of b");
+    info!(a, "second of a");
+    let html = log.raw_html();
+    assert_eq!(html.matches(&first_delta("\u{394}ctx ")).count(), 2, "{}", html);
+}
+
+#[test]
+fn new_loggers_do_not_inherit_context_delta() {
+    let log = TestLog::new(slog_html::new().context_delta_time(true).delta_time(true));
+    // Loggers dropped in turn are likely to be allocated at the same address
+    for i in 0..3 {
+        let child = log.logger().new(o!("request" => i));
+        info!(child, "handled");
+    }
+    let html = log.raw_html();
+    assert_eq!(html.matches(&first_delta("\u{394}ctx ")).count(), 3, "{}", html);
+    assert_eq!(html.matches(&first_delta("\u{394}")).count(), 1, "{}", html);
+}
+
+#[test]
+fn deltas_above_threshold_are_gaps() {
+    let log = gap_log(slog_html::new().delta_time(true));
+    info!(log.logger(), "first");
+    info!(log.logger(), "right after");
+    thread::sleep(THRESHOLD * 2);
+    info!(log.logger(), "after a pause");
+    let html = log.raw_html();
+    let lines: Vec<&str> = html.lines().collect();
+    assert!(!is_gap(lines[0], "\u{394} "));
+    assert!(!is_gap(lines[1], "\u{394} "));
+    assert!(is_gap(lines[2], "\u{394} "));
+}
+
+#[test]
+fn context_gaps_are_apart_from_global_gaps() {
+    let log = gap_log(slog_html::new().delta_time(true).context_delta_time(true));
+    let a = log.logger().new(o!("logger" => "a"));
+    let b = log.logger().new(o!("logger" => "b"));
+    info!(a, "first of a");
+    thread::sleep(THRESHOLD * 2);
+    info!(b, "first of b");
+    info!(a, "second of a");
+    let html = log.raw_html();
+    let lines: Vec<&str> = html.lines().collect();
+    assert!(is_gap(lines[1], "\u{394} "));
+    assert!(!is_gap(lines[1], "\u{394}ctx "));
+    assert!(!is_gap(lines[2], "\u{394} "));
+    assert!(is_gap(lines[2], "\u{394}ctx "));
+}
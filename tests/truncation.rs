@@ -0,0 +1,45 @@
+//! Oversized messages and values
+
+#[macro_use]
+extern crate slog;
+extern crate slog_html;
+
+use slog::Level;
+use slog_html::Truncation;
+use slog_html::testing::TestLog;
+
+#[test]
+fn truncated_text_expands_on_click() {
+    let log = TestLog::new(slog_html::new().max_message_len(5).max_value_len(4));
+    info!(log.logger(), "0123456789"; "short" => "abcd", "long" => "abcdef");
+    let html = log.html();
+    assert!(html.contains(">01234</span><details style=\"display:inline-block;\
+                           vertical-align:top\"><summary>\u{2026} (+5 bytes)</summary>"),
+            "{}",
+            html);
+    assert!(html.contains(">abcd</span><details"), "{}", html);
+    assert_eq!(html.matches("<details").count(), 2, "{}", html);
+    // The whole text is kept
+    log.assert_message(Level::Info, "0123456789");
+    log.assert_record(Level::Info, "short", "abcd");
+    log.assert_record(Level::Info, "long", "abcdef");
+}
+
+#[test]
+fn truncation_keeps_whole_characters() {
+    let builder = slog_html::new()
+        .max_message_len(5)
+        .max_value_len(3)
+        .truncation(Truncation::Drop);
+    let log = TestLog::new(builder);
+    // `é` takes bytes 4 and 5, the rocket bytes 1 to 4
+    info!(log.logger(), "caf\u{e9}s et th\u{e9}"; "v" => "\u{1f680}x", "w" => "d\u{e9}j\u{e0}");
+    let html = log.html();
+    assert!(html.contains(">caf\u{e9}</span>\u{2026} (+9 bytes)"), "{}", html);
+    assert!(html.contains("></span>\u{2026} (+5 bytes)"), "{}", html);
+    assert!(html.contains(">d\u{e9}</span>\u{2026} (+3 bytes)"), "{}", html);
+    assert!(!html.contains("<details"), "{}", html);
+    // The truncated part is dropped
+    log.assert_message(Level::Info, "caf\u{e9}");
+    log.assert_record(Level::Info, "w", "d\u{e9}");
+}